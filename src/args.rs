@@ -1,9 +1,15 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 pub mod value;
 pub mod branches;
+pub mod revision;
+pub mod stash;
+pub mod trash;
 
-use branches::BranchComand;
+use branches::{BranchAction, BranchComand};
+use revision::Revision;
+use stash::{StashAction, StashCommand};
+use trash::{TrashAction, TrashCommand};
 
 #[derive(Parser, Debug)]
 #[command()]
@@ -11,6 +17,19 @@ use branches::BranchComand;
 pub struct CliArgs {
     #[command(subcommand)]
     pub action_context: ActionContext,
+
+    /// How branch list, status, log, diff and save print their result
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    pub output: OutputFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Readable text
+    Human,
+    /// One json document on stdout, an error object when the command fails.
+    /// Notices, progress and failure messages go to stderr.
+    Json,
 }
 
 #[derive(Args, Debug)]
@@ -18,6 +37,130 @@ pub struct InitCommand {
     pub folderName: Option<String>,
 }
 
+#[derive(Args, Debug)]
+pub struct RestoreCommand {
+    /// The revision to restore, defaults to the latest version of the current branch
+    pub branch: Option<Revision>,
+
+    /// The version to restore, defaults to the latest version
+    #[arg(short, long)]
+    pub version: Option<i32>,
+
+    /// Only restore the files matching this path or glob (relative to src), can be repeated
+    #[arg(short, long = "file")]
+    pub files: Vec<String>,
+
+    /// Restore the whole of src even if it has unsaved changes, discarding them
+    #[arg(long, conflicts_with = "files")]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SaveCommand {
+    /// A note describing what changed in this version
+    #[arg(short, long)]
+    pub message: Option<String>,
+
+    /// A tag to give this version, can be repeated
+    #[arg(short, long = "tag")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct LogCommand {
+    /// The branch to show the history of, a revision like branch@5 or a tag stops at that version
+    pub branch: Option<Revision>,
+
+    /// Only show the latest versions
+    #[arg(short = 'n', long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffCommand {
+    /// The version to compare from as branch@version, defaults to the latest version of the current branch
+    pub from: Option<Revision>,
+
+    /// The version to compare to as branch@version, defaults to the src folder
+    pub to: Option<Revision>,
+}
+
+#[derive(Args, Debug)]
+pub struct PickCommand {
+    /// The version to take the files from as branch@version
+    pub from: Revision,
+
+    /// The paths or globs of the files to take, relative to src
+    #[arg(required = true)]
+    pub files: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct PruneCommand {
+    /// The branch to prune, defaults to the current branch
+    pub branch: Option<String>,
+
+    /// Prune every branch of the workspace
+    #[arg(short, long, conflicts_with = "branch")]
+    pub all: bool,
+
+    /// Keep the latest N versions
+    #[arg(long, value_name = "N")]
+    pub keep_last: Option<usize>,
+
+    /// Keep the versions that have tags
+    #[arg(long)]
+    pub keep_tagged: bool,
+
+    /// Only prune versions older than this age, like 12h, 30d or 2w
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    pub older_than: Option<chrono::Duration>,
+
+    /// Keep the newest version of every day
+    #[arg(long)]
+    pub daily: bool,
+
+    /// Only report what would be removed
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+fn parse_age(age: &str) -> Result<chrono::Duration, String> {
+    let invalid = || format!("Invalid age '{}', use a number followed by h, d or w.", age);
+
+    let (amount, unit) = match age.char_indices().last() {
+        Some((index, unit)) => (&age[..index], unit),
+        None => return Err(invalid()),
+    };
+
+    // a negative age would put the cutoff in the future and make every version old enough
+    let amount: u32 = amount.parse().map_err(|_| invalid())?;
+    let amount = i64::from(amount);
+
+    let duration = match unit {
+        'h' => chrono::Duration::try_hours(amount),
+        'd' => chrono::Duration::try_days(amount),
+        'w' => chrono::Duration::try_weeks(amount),
+        _ => None,
+    };
+
+    duration.ok_or_else(invalid)
+}
+
+#[derive(Args, Debug)]
+pub struct TagCommand {
+    /// The name of the tag, lists the tags when left out
+    pub name: Option<String>,
+
+    /// The version to tag as branch@version, defaults to the latest version of the current branch
+    #[arg(conflicts_with = "delete")]
+    pub target: Option<Revision>,
+
+    /// Remove the tag instead
+    #[arg(short, long, requires = "name")]
+    pub delete: bool,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum ActionContext {
 
@@ -28,5 +171,56 @@ pub enum ActionContext {
     Branch(BranchComand),
 
     /// Save the current state of the mod to the current branch
-    Save,
+    Save(SaveCommand),
+
+    /// Replace the src folder with a saved version of a branch
+    Restore(RestoreCommand),
+
+    /// Show the saved versions of a branch
+    Log(LogCommand),
+
+    /// Show the files added, removed and modified between src and a saved version or between two versions
+    Diff(DiffCommand),
+
+    /// Show the current branch and the changes in src that are not saved yet
+    Status,
+
+    /// Park unsaved changes of src without saving a version
+    Stash(StashCommand),
+
+    /// Copy files from any branch version into src
+    Pick(PickCommand),
+
+    /// Manage the deleted branches
+    Trash(TrashCommand),
+
+    /// Remove old versions and the stored files nothing uses anymore
+    Prune(PruneCommand),
+
+    /// Give a saved version a name that can be used wherever a version is expected
+    Tag(TagCommand),
+}
+
+impl ActionContext {
+    /// Commands that only look at the workspace, they skip the workspace lock and don't save
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            ActionContext::Log(_) | ActionContext::Diff(_) | ActionContext::Status => true,
+            ActionContext::Branch(b) => matches!(b.action, BranchAction::List | BranchAction::Graph),
+            ActionContext::Stash(s) => matches!(s.action, StashAction::List),
+            ActionContext::Trash(t) => matches!(t.action, TrashAction::List),
+            ActionContext::Tag(t) => t.name.is_none(),
+            ActionContext::Prune(p) => p.dry_run,
+            _ => false,
+        }
+    }
+
+    /// Commands that print json with `--output json`, the others always print text
+    pub fn has_json_output(&self) -> bool {
+        match self {
+            ActionContext::Log(_) | ActionContext::Diff(_) | ActionContext::Status | ActionContext::Save(_) => true,
+            ActionContext::Branch(b) => matches!(b.action, BranchAction::List),
+            _ => false,
+        }
+    }
 }
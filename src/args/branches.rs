@@ -1,6 +1,6 @@
 use clap::{Args, Subcommand};
 
-use super::value::Value;
+use super::{revision::Revision, value::Value};
 
 #[derive(Debug, Args)]
 pub struct BranchComand {
@@ -10,7 +10,20 @@ pub struct BranchComand {
 
 #[derive(Debug, Args)]
 pub struct SwitchBranch {
-    pub branch: String,
+    /// the branch to switch to, or a revision like branch@3, branch@~1 or a tag to switch to that version
+    pub branch: Revision,
+
+    /// discard the unsaved changes in src
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// save the unsaved changes in src to the current branch before switching
+    #[arg(short, long, conflicts_with = "force")]
+    pub save_first: bool,
+
+    /// stash the unsaved changes in src before switching
+    #[arg(long, conflicts_with_all = ["force", "save_first"])]
+    pub stash: bool,
 }
 
 // create branch
@@ -25,6 +38,53 @@ pub struct CreateBranch {
     /// save the workspace after swapping
     #[arg(short='S')]
     pub save: bool,
+
+    /// start the branch from an existing version, written as branch@version
+    #[arg(short, long)]
+    pub from: Option<Revision>,
+
+    /// describe what the branch is for
+    #[arg(short, long)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct DeleteBranch {
+    pub branch: String,
+
+    /// don't ask for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// allow deleting the current branch
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RenameBranch {
+    pub branch: String,
+    pub new_name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct DescribeBranch {
+    pub branch: String,
+    pub description: String,
+}
+
+#[derive(Debug, Args)]
+pub struct MergeBranch {
+    /// the branch to merge into the current branch
+    pub branch: String,
+
+    /// keep the current branch's file for conflicts matching this glob, can be repeated
+    #[arg(long)]
+    pub ours: Vec<String>,
+
+    /// take the merged branch's file for conflicts matching this glob, can be repeated
+    #[arg(long)]
+    pub theirs: Vec<String>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -32,6 +92,17 @@ pub enum BranchAction {
     /// Switch to a different branch
     Switch(SwitchBranch),
     Create(CreateBranch),
-    Delete(Value),
+    /// Move a branch and its versions to the trash
+    Delete(DeleteBranch),
+    /// Bring a deleted branch back from the trash
+    Undelete(Value),
     List,
+    /// Show the branches as a tree of where they were created from
+    Graph,
+    /// Merge the latest version of a branch into src
+    Merge(MergeBranch),
+    /// Give a branch a new name
+    Rename(RenameBranch),
+    /// Change the description of a branch
+    Describe(DescribeBranch),
 }
@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+/// Which version of a branch a revision points to
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum VersionSpec {
+    /// The latest saved version, written as `@latest` or left out
+    #[default]
+    Latest,
+    /// An exact version number like `@7`
+    Number(i32),
+    /// Counted back from the latest version, `@~2` is two before it and `@prev` is `@~1`
+    Back(i32),
+}
+
+/// A saved version written as `branch@version`, both parts can be left out
+/// to fall back to the current branch and its latest version.
+/// A bare name that isn't a branch is looked up as a tag.
+#[derive(Debug, Clone, Default)]
+pub struct Revision {
+    pub branch: Option<String>,
+    pub version: VersionSpec,
+}
+
+impl Revision {
+    /// The latest version of a branch
+    pub fn branch(name: String) -> Revision {
+        Revision {
+            branch: Some(name),
+            version: VersionSpec::Latest,
+        }
+    }
+}
+
+impl FromStr for Revision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("Empty revision, expected branch, branch@version or a tag.".to_string());
+        }
+
+        let (branch, version) = match s.split_once('@') {
+            Some((branch, version)) => (branch, Some(version)),
+            None => (s, None),
+        };
+
+        let branch = if branch.is_empty() {
+            None
+        } else {
+            Some(branch.to_string())
+        };
+
+        let invalid = |v: &str| {
+            format!(
+                "Invalid version '{}' in '{}', expected a number, latest, prev or ~N.",
+                v, s
+            )
+        };
+
+        let version = match version {
+            None | Some("latest") => VersionSpec::Latest,
+            Some("prev") => VersionSpec::Back(1),
+            Some(v) => match v.strip_prefix('~') {
+                // a lone ~ steps back once like in git
+                Some("") => VersionSpec::Back(1),
+                Some(n) => match n.parse::<i32>() {
+                    Ok(n) if n >= 0 => VersionSpec::Back(n),
+                    _ => return Err(invalid(v)),
+                },
+                None => VersionSpec::Number(v.parse::<i32>().map_err(|_| invalid(v))?),
+            },
+        };
+
+        Ok(Revision { branch, version })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Revision, VersionSpec};
+
+    fn parse(s: &str) -> Revision {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn bare_branch_is_its_latest_version() {
+        let revision = parse("main");
+
+        assert_eq!(revision.branch.as_deref(), Some("main"));
+        assert_eq!(revision.version, VersionSpec::Latest);
+        assert_eq!(parse("main@latest").version, VersionSpec::Latest);
+    }
+
+    #[test]
+    fn version_numbers() {
+        let revision = parse("textures@7");
+
+        assert_eq!(revision.branch.as_deref(), Some("textures"));
+        assert_eq!(revision.version, VersionSpec::Number(7));
+    }
+
+    #[test]
+    fn versions_counted_back() {
+        assert_eq!(parse("main@prev").version, VersionSpec::Back(1));
+        assert_eq!(parse("main@~").version, VersionSpec::Back(1));
+        assert_eq!(parse("main@~3").version, VersionSpec::Back(3));
+        assert_eq!(parse("main@~0").version, VersionSpec::Back(0));
+    }
+
+    #[test]
+    fn branch_can_be_left_out() {
+        let revision = parse("@~2");
+
+        assert_eq!(revision.branch, None);
+        assert_eq!(revision.version, VersionSpec::Back(2));
+        assert_eq!(parse("@prev").branch, None);
+        assert_eq!(parse("@4").version, VersionSpec::Number(4));
+    }
+
+    #[test]
+    fn tags_parse_like_branches() {
+        let revision = parse("release@~2");
+
+        assert_eq!(revision.branch.as_deref(), Some("release"));
+        assert_eq!(revision.version, VersionSpec::Back(2));
+    }
+
+    #[test]
+    fn malformed_revisions_are_rejected() {
+        for invalid in ["", "main@", "main@x", "main@~x", "main@~-1", "main@1.5", "@", "main@~~"] {
+            assert!(invalid.parse::<Revision>().is_err(), "{} should not parse", invalid);
+        }
+    }
+}
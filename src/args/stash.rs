@@ -0,0 +1,33 @@
+use clap::{Args, Subcommand};
+
+#[derive(Debug, Args)]
+pub struct StashCommand {
+    #[clap(subcommand)]
+    pub action: StashAction,
+}
+
+#[derive(Debug, Args)]
+pub struct PushStash {
+    /// a note describing the stashed changes
+    #[arg(short, long)]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct StashIndex {
+    /// the stash to use as shown by the list command, defaults to the latest one
+    #[arg(default_value_t = 0)]
+    pub index: usize,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StashAction {
+    /// Park the unsaved changes in src and bring src back to the latest version
+    Push(PushStash),
+    /// List the stashed changes, latest first
+    List,
+    /// Apply stashed changes to src and remove them from the stash
+    Pop(StashIndex),
+    /// Remove stashed changes without applying them
+    Drop(StashIndex),
+}
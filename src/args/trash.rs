@@ -0,0 +1,22 @@
+use clap::{Args, Subcommand};
+
+#[derive(Debug, Args)]
+pub struct TrashCommand {
+    #[clap(subcommand)]
+    pub action: TrashAction,
+}
+
+#[derive(Debug, Args)]
+pub struct EmptyTrash {
+    /// don't ask for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TrashAction {
+    /// List the deleted branches
+    List,
+    /// Permanently remove the deleted branches
+    Empty(EmptyTrash),
+}
@@ -1,11 +1,50 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 
+/// Names of branches and tags can't contain separators, an @ or start with a dot
+pub fn is_valid_name(name: &str) -> bool {
+    !(name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\', '@', ':'])
+        || name.trim() != name)
+}
+
+/// The branch and version a branch was created from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchParent {
+    pub branch: String,
+    /// 0 when the parent had no saved version yet
+    pub version: i32,
+}
+
+/// A merge saved on a branch, everything of the source version is part of the branch from `version` on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeRecord {
+    pub source: BranchParent,
+    pub version: i32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Branch {
     pub name: String,
     pub description: String,
     pub version: i32,
+    #[serde(default)]
+    pub parent: Option<BranchParent>,
+    #[serde(default)]
+    pub merges: Vec<MergeRecord>,
+    /// A merge that is in src but not saved yet, it becomes a merge record with the next save
+    #[serde(default)]
+    pub pending_merge: Option<BranchParent>,
+}
+
+/// A deleted branch waiting in the trash, its folder is kept under its id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedBranch {
+    pub id: String,
+    pub branch: Branch,
+    pub deleted: DateTime<Utc>,
 }
 
 impl Branch {
@@ -14,6 +53,18 @@ impl Branch {
             name,
             description,
             version,
+            parent: None,
+            merges: vec![],
+            pending_merge: None,
         }
     }
-}
\ No newline at end of file
+
+    /// The latest saved version, None if the branch was never saved
+    pub fn latest_version(&self) -> Option<i32> {
+        if self.version > 1 {
+            Some(self.version - 1)
+        } else {
+            None
+        }
+    }
+}
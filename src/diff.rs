@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// The files that differ between two sets of relative paths mapped to content hashes
+#[derive(Debug, Default, Serialize)]
+pub struct FileChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl FileChanges {
+    pub fn between(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> FileChanges {
+        let mut changes = FileChanges::default();
+
+        for (path, hash) in new {
+            match old.get(path) {
+                None => changes.added.push(path.clone()),
+                Some(old_hash) if old_hash != hash => changes.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+
+        for path in old.keys() {
+            if !new.contains_key(path) {
+                changes.removed.push(path.clone());
+            }
+        }
+
+        changes
+    }
+
+    pub fn len(&self) -> usize {
+        self.added.len() + self.removed.len() + self.modified.len()
+    }
+
+    pub fn contains(&self, path: &String) -> bool {
+        self.added.contains(path) || self.removed.contains(path) || self.modified.contains(path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
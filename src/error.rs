@@ -0,0 +1,177 @@
+use std::{fmt, path::PathBuf};
+
+/// A single file an operation couldn't copy, store or create
+#[derive(Debug)]
+pub struct FileFailure {
+    pub path: PathBuf,
+    pub error: std::io::Error,
+}
+
+impl FileFailure {
+    pub fn new(path: PathBuf, error: std::io::Error) -> FileFailure {
+        FileFailure { path, error }
+    }
+}
+
+/// Everything a command can fail with. Each variant exits the process with its own
+/// code, listed on the variant, so scripts can tell failures apart. Code 2 is left
+/// to clap, which uses it for arguments it can't parse.
+#[derive(Debug)]
+pub enum ModderError {
+    /// Exit code 1: reading or writing a file failed
+    Io(std::io::Error),
+    /// Exit code 24: a request that can't be carried out as given
+    Invalid(String),
+    /// Exit code 3: no `.info` in the current folder or any folder above it
+    WorkspaceNotFound,
+    /// Exit code 4: `init` in a folder that already holds a workspace
+    WorkspaceExists,
+    /// Exit code 5: another ModderCli holds the workspace lock
+    WorkspaceBusy { owner: String, lock_file: PathBuf },
+    /// Exit code 6: the command works on the current branch but none is selected
+    NoBranchSelected,
+    /// Exit code 7
+    BranchNotFound(String),
+    /// Exit code 8
+    BranchExists(String),
+    /// Exit code 9: deleting the current branch without forcing it
+    CurrentBranch(String),
+    /// Exit code 10
+    TrashedBranchNotFound(String),
+    /// Exit code 11: a branch or tag name that can't be used, the kind says which of the two
+    InvalidName { kind: &'static str, name: String },
+    /// Exit code 12: a name that is neither a branch nor a tag, with the branches that do exist
+    UnknownRevision { name: String, branches: Vec<String> },
+    /// Exit code 13
+    NoVersions(String),
+    /// Exit code 14
+    VersionNotFound { branch: String, version: i32, latest: i32 },
+    /// Exit code 15: the version was counted but its files are gone, usually removed by prune
+    VersionPruned { branch: String, version: i32 },
+    /// Exit code 16
+    TagNotFound(String),
+    /// Exit code 17
+    TagExists(String),
+    /// Exit code 18
+    StashNotFound(usize),
+    /// Exit code 19: changes in src the command would otherwise throw away
+    DirtySrc(String),
+    /// Exit code 20: a merge stopped on files changed on both branches
+    Conflicts(usize),
+    /// Exit code 21: the user answered no to a confirmation
+    Cancelled,
+    /// Exit code 22: files that failed while the rest of the operation was attempted,
+    /// the action says what was being done with them
+    Files { action: &'static str, failures: Vec<FileFailure> },
+    /// Exit code 23: workspace metadata that can't be read even from its backup, or a broken manifest
+    Parse(serde_json::Error),
+}
+
+impl ModderError {
+    /// `Ok` when nothing failed, otherwise every failure in one error
+    pub fn check_files(action: &'static str, failures: Vec<FileFailure>) -> Result<(), ModderError> {
+        if failures.is_empty() {
+            return Ok(());
+        }
+
+        Err(ModderError::Files { action, failures })
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ModderError::Io(_) => 1,
+            ModderError::Invalid(_) => 24,
+            ModderError::WorkspaceNotFound => 3,
+            ModderError::WorkspaceExists => 4,
+            ModderError::WorkspaceBusy { .. } => 5,
+            ModderError::NoBranchSelected => 6,
+            ModderError::BranchNotFound(_) => 7,
+            ModderError::BranchExists(_) => 8,
+            ModderError::CurrentBranch(_) => 9,
+            ModderError::TrashedBranchNotFound(_) => 10,
+            ModderError::InvalidName { .. } => 11,
+            ModderError::UnknownRevision { .. } => 12,
+            ModderError::NoVersions(_) => 13,
+            ModderError::VersionNotFound { .. } => 14,
+            ModderError::VersionPruned { .. } => 15,
+            ModderError::TagNotFound(_) => 16,
+            ModderError::TagExists(_) => 17,
+            ModderError::StashNotFound(_) => 18,
+            ModderError::DirtySrc(_) => 19,
+            ModderError::Conflicts(_) => 20,
+            ModderError::Cancelled => 21,
+            ModderError::Files { .. } => 22,
+            ModderError::Parse(_) => 23,
+        }
+    }
+}
+
+impl fmt::Display for ModderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModderError::Io(e) => write!(f, "{}", e),
+            ModderError::Invalid(message) => write!(f, "{}", message),
+            ModderError::WorkspaceNotFound => write!(f, "Workspace not found."),
+            ModderError::WorkspaceExists => write!(f, "Workspace already exists."),
+            ModderError::WorkspaceBusy { owner, lock_file } => write!(
+                f,
+                "Workspace busy, {} is using it. If no other ModderCli is running remove {}.",
+                owner,
+                lock_file.display()
+            ),
+            ModderError::NoBranchSelected => write!(f, "No Branches Selected."),
+            ModderError::BranchNotFound(name) => write!(f, "Branch {} not found.", name),
+            ModderError::BranchExists(name) => write!(f, "Branch {} already exists.", name),
+            ModderError::CurrentBranch(name) => {
+                write!(f, "Cannot delete the current branch {} without forcing it.", name)
+            }
+            ModderError::TrashedBranchNotFound(name) => write!(f, "Branch {} not found in the trash.", name),
+            ModderError::InvalidName { kind, name } => write!(f, "Invalid {} name '{}'.", kind, name),
+            ModderError::UnknownRevision { name, branches } => write!(
+                f,
+                "'{}' is neither a branch nor a tag. Branches: {}.",
+                name,
+                branches.join(", ")
+            ),
+            ModderError::NoVersions(name) => write!(f, "Branch {} has no saved versions.", name),
+            ModderError::VersionNotFound { branch, version, latest } => write!(
+                f,
+                "Version {} does not exist in branch {}, the latest is {}.",
+                version, branch, latest
+            ),
+            ModderError::VersionPruned { branch, version } => {
+                write!(f, "Version {} of branch {} was pruned.", version, branch)
+            }
+            ModderError::TagNotFound(name) => write!(f, "Tag {} not found.", name),
+            ModderError::TagExists(name) => write!(f, "Tag {} already exists.", name),
+            ModderError::StashNotFound(index) => write!(f, "Stash {} not found.", index),
+            ModderError::DirtySrc(message) => write!(f, "{}", message),
+            ModderError::Conflicts(count) => write!(f, "{} file(s) have conflicting changes.", count),
+            ModderError::Cancelled => write!(f, "Cancelled."),
+            ModderError::Files { action, failures } => {
+                write!(f, "Could not {} {} file(s):", action, failures.len())?;
+
+                for failure in failures {
+                    write!(f, "\n  {}: {}", failure.path.display(), failure.error)?;
+                }
+
+                Ok(())
+            }
+            ModderError::Parse(e) => write!(f, "Invalid workspace data: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ModderError {}
+
+impl From<std::io::Error> for ModderError {
+    fn from(e: std::io::Error) -> Self {
+        ModderError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ModderError {
+    fn from(e: serde_json::Error) -> Self {
+        ModderError::Parse(e)
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use glob::{MatchOptions, Pattern};
+
+use crate::error::ModderError;
+
+/// A single line of the `.ignore` file
+#[derive(Debug)]
+struct IgnoreRule {
+    pattern: Pattern,
+    negated: bool,
+    directory_only: bool,
+}
+
+/// Gitignore style patterns read from the `.ignore` file, matched against paths relative to src
+#[derive(Debug, Default)]
+pub struct IgnorePatterns {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnorePatterns {
+    pub fn parse(content: &str) -> Result<IgnorePatterns, ModderError> {
+        let mut rules = vec![];
+
+        for line in content.lines() {
+            let line = line.trim_end();
+
+            // skip empty lines and comments
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(l) => (true, l),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+
+            let (directory_only, line) = match line.strip_suffix('/') {
+                Some(l) => (true, l),
+                None => (false, line),
+            };
+
+            // a pattern with a slash is anchored to src, otherwise it matches at any depth
+            let pattern = match line.strip_prefix('/') {
+                Some(l) => l.to_string(),
+                None if line.contains('/') => line.to_string(),
+                None => format!("**/{}", line),
+            };
+
+            let pattern = Pattern::new(&pattern)
+                .map_err(|e| ModderError::Invalid(format!("Invalid ignore pattern {}: {}", line, e)))?;
+
+            rules.push(IgnoreRule {
+                pattern,
+                negated,
+                directory_only,
+            });
+        }
+
+        Ok(IgnorePatterns { rules })
+    }
+
+    /// Checks a single entry, the last matching rule wins like in git.
+    /// Callers walking a tree should not descend into ignored folders.
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let path = relative.to_string_lossy().replace('\\', "/");
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+
+        let mut ignored = false;
+
+        for rule in &self.rules {
+            if rule.directory_only && !is_dir {
+                continue;
+            }
+
+            if rule.pattern.matches_with(&path, options) {
+                ignored = !rule.negated;
+            }
+        }
+
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::IgnorePatterns;
+    use crate::error::ModderError;
+
+    fn ignored(patterns: &IgnorePatterns, path: &str) -> bool {
+        patterns.is_ignored(Path::new(path), false)
+    }
+
+    #[test]
+    fn unanchored_patterns_match_at_any_depth() {
+        let patterns = IgnorePatterns::parse("*.tmp").unwrap();
+
+        assert!(ignored(&patterns, "a.tmp"));
+        assert!(ignored(&patterns, "textures/deep/a.tmp"));
+        assert!(!ignored(&patterns, "a.dds"));
+    }
+
+    #[test]
+    fn leading_slash_anchors_to_src() {
+        let patterns = IgnorePatterns::parse("/notes.txt").unwrap();
+
+        assert!(ignored(&patterns, "notes.txt"));
+        assert!(!ignored(&patterns, "docs/notes.txt"));
+    }
+
+    #[test]
+    fn pattern_with_a_slash_is_anchored() {
+        let patterns = IgnorePatterns::parse("docs/*.md").unwrap();
+
+        assert!(ignored(&patterns, "docs/readme.md"));
+        assert!(!ignored(&patterns, "parts/docs/readme.md"));
+        // * doesn't cross folders
+        assert!(!ignored(&patterns, "docs/old/readme.md"));
+    }
+
+    #[test]
+    fn negation_re_includes_and_last_rule_wins() {
+        let patterns = IgnorePatterns::parse("*.log\n!keep.log").unwrap();
+
+        assert!(ignored(&patterns, "debug.log"));
+        assert!(!ignored(&patterns, "keep.log"));
+        assert!(!ignored(&patterns, "logs/keep.log"));
+
+        let patterns = IgnorePatterns::parse("!keep.log\n*.log").unwrap();
+
+        assert!(ignored(&patterns, "keep.log"));
+    }
+
+    #[test]
+    fn directory_rules_only_match_folders() {
+        let patterns = IgnorePatterns::parse("cache/").unwrap();
+
+        assert!(patterns.is_ignored(Path::new("cache"), true));
+        assert!(patterns.is_ignored(Path::new("parts/cache"), true));
+        assert!(!patterns.is_ignored(Path::new("cache"), false));
+    }
+
+    #[test]
+    fn comments_blank_lines_and_escapes() {
+        let patterns = IgnorePatterns::parse("# *.dds\n\n\\!important.txt\n").unwrap();
+
+        assert!(!ignored(&patterns, "a.dds"));
+        assert!(ignored(&patterns, "!important.txt"));
+        assert!(!ignored(&patterns, "important.txt"));
+    }
+
+    #[test]
+    fn windows_separators_are_matched_like_slashes() {
+        let patterns = IgnorePatterns::parse("docs/*.md").unwrap();
+
+        assert!(ignored(&patterns, "docs\\readme.md"));
+    }
+
+    #[test]
+    fn invalid_pattern_is_rejected() {
+        assert!(matches!(IgnorePatterns::parse("[a"), Err(ModderError::Invalid(_))));
+    }
+}
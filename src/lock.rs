@@ -0,0 +1,130 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::error::ModderError;
+
+/// Advisory lock held while a command changes the workspace, the lock file holds the PID of
+/// its owner so a lock left behind by a crashed or killed process can be taken over
+#[derive(Debug)]
+pub struct WorkspaceLock {
+    path: PathBuf,
+}
+
+impl WorkspaceLock {
+    pub fn acquire(root_folder: &Path) -> Result<WorkspaceLock, ModderError> {
+        let path = root_folder.join(".lock");
+
+        // one retry after clearing a stale lock, another process may win the race for it
+        for _ in 0..2 {
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    write!(file, "{}", std::process::id())?;
+                    return Ok(WorkspaceLock { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            // an empty file is a lock that is still being written
+            let owner = match std::fs::read_to_string(&path) {
+                Ok(owner) => owner,
+                // released since, try again
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            match owner.trim().parse::<u32>() {
+                Ok(pid) if !process_running(pid) => {
+                    if WorkspaceLock::take_over(&path, &owner)? {
+                        eprintln!("Removed stale workspace lock of process {}.", pid);
+                    }
+                }
+                Ok(pid) => {
+                    return Err(WorkspaceLock::busy(&path, &format!("process {}", pid)));
+                }
+                Err(_) => {
+                    return Err(WorkspaceLock::busy(&path, "another process"));
+                }
+            }
+        }
+
+        Err(WorkspaceLock::busy(&path, "another process"))
+    }
+
+    /// Moves a stale lock out of the way. Another process may have replaced it since it was
+    /// read, so it's renamed to a name of our own first and put back if it isn't the stale one.
+    fn take_over(path: &Path, stale_owner: &str) -> Result<bool, ModderError> {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".stale-{}", std::process::id()));
+        let moved = path.with_file_name(name);
+
+        match std::fs::rename(path, &moved) {
+            Ok(_) => {}
+            // someone else took it over first
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+
+        if std::fs::read_to_string(&moved)? != stale_owner {
+            // a live lock, linking never replaces a lock created in the meantime
+            let restored = std::fs::hard_link(&moved, path);
+            _ = std::fs::remove_file(&moved);
+            restored?;
+
+            return Ok(false);
+        }
+
+        std::fs::remove_file(&moved)?;
+
+        Ok(true)
+    }
+
+    fn busy(path: &Path, owner: &str) -> ModderError {
+        ModderError::WorkspaceBusy {
+            owner: owner.to_string(),
+            lock_file: path.to_path_buf(),
+        }
+    }
+}
+
+impl Drop for WorkspaceLock {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn process_running(pid: u32) -> bool {
+    // unlike kill -0, ps also finds processes of other users
+    let status = std::process::Command::new("ps")
+        .args(["-p", &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status();
+
+    match status {
+        Ok(status) => status.success(),
+        // without a way to check, assume the owner is still alive
+        Err(_) => true,
+    }
+}
+
+#[cfg(windows)]
+fn process_running(pid: u32) -> bool {
+    let output = std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output();
+
+    match output {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()),
+        // without a way to check, assume the owner is still alive
+        Err(_) => true,
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_running(_pid: u32) -> bool {
+    true
+}
@@ -1,108 +1,232 @@
 #![allow(non_snake_case)]
-use args::{branches, ActionContext};
-use clap::{error::Error, Parser};
+use std::process::ExitCode;
+
+use args::{
+    branches,
+    revision::{Revision, VersionSpec},
+    ActionContext, CliArgs, OutputFormat,
+};
+use clap::Parser;
+use serde::Serialize;
 use mod_info::ModInfo;
 use workspace_handler::Workspace;
 
-use crate::workspace_handler::SwitchResult;
+use crate::{
+    diff::FileChanges,
+    stash::Stash,
+    tag::Tag,
+    error::ModderError,
+    workspace_handler::{MergeResult, PrunePolicy, PruneReport, Status, SwitchResult, VersionLog},
+};
 
 mod args;
 mod branch;
+mod diff;
+mod error;
+mod ignore;
+mod lock;
+mod manifest;
+mod metadata;
 mod mod_info;
+mod object_store;
+mod stash;
+mod tag;
 mod workspace_handler;
 
-fn main() -> Result<(), Error> {
+fn main() -> ExitCode {
     let args = args::CliArgs::parse();
 
-    let workspace = Workspace::load_workspace();
+    let output = if args.action_context.has_json_output() {
+        args.output
+    } else {
+        OutputFormat::Human
+    };
 
-    let mut workspace = match workspace {
-        Ok(workspace) => workspace,
-        Err(err) => match err.kind() {
-            std::io::ErrorKind::NotFound => {
-                match args.action_context {
-                    ActionContext::Init(command) => {
-                        let mut current_folder = std::env::current_dir()?;
+    // the exit code tells scripts which failure it was, see ModderError for the list
+    match run(args, output) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            if output == OutputFormat::Json {
+                _ = PrintJson(&ErrorJson {
+                    error: e.to_string(),
+                    code: e.exit_code(),
+                });
+            }
 
-                        if let Some(folder_name) = command.folderName {
-                            current_folder.push(folder_name);
-                        }
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
 
-                        println!(
-                            "Initializing workspace in: {},",
-                            current_folder.to_str().unwrap()
-                        );
+fn run(args: CliArgs, output: OutputFormat) -> Result<(), ModderError> {
+    let read_only = args.action_context.is_read_only();
 
-                        let mod_info = getModInfoFromUser();
-                        let result = Workspace::init(current_folder, mod_info);
+    // held from loading until everything is saved so two invocations never interleave
+    let lock = if read_only {
+        None
+    } else {
+        match Workspace::lock() {
+            Ok(lock) => lock,
+            Err(e) => {
+                PrintFailure(output, format!("Failed to lock workspace: {}", e));
+                return Err(e);
+            }
+        }
+    };
 
-                        match result {
-                            Ok(workspace) => workspace,
-                            Err(e) => {
-                                println!("Failed to initialize workspace: {}", e);
-                                return Ok(());
-                            }
-                        };
+    let workspace = Workspace::load_workspace(lock.as_ref());
 
-                        return Ok(());
+    let mut workspace = match workspace {
+        Ok(workspace) => workspace,
+        Err(ModderError::WorkspaceNotFound) => {
+            match args.action_context {
+                ActionContext::Init(command) => {
+                    let mut current_folder = std::env::current_dir()?;
+
+                    if let Some(folder_name) = command.folderName {
+                        current_folder.push(folder_name);
                     }
-                    _ => {
-                        println!("You need to initialize a workspace first.\nUse 'ModderCli -h' for help.");
-                        return Ok(());
+
+                    println!(
+                        "Initializing workspace in: {},",
+                        current_folder.to_str().unwrap()
+                    );
+
+                    let mod_info = getModInfoFromUser();
+
+                    if let Err(e) = Workspace::init(current_folder, mod_info) {
+                        println!("Failed to initialize workspace: {}", e);
+                        return Err(e);
                     }
-                };
-            }
-            _ => {
-                println!("Failed to load workspace: {}", err);
-                return Ok(());
-            }
-        },
+
+                    return Ok(());
+                }
+                _ => {
+                    PrintFailure(output, "You need to initialize a workspace first.\nUse 'ModderCli -h' for help.".to_string());
+                    return Err(ModderError::WorkspaceNotFound);
+                }
+            };
+        }
+        Err(e) => {
+            PrintFailure(output, format!("Failed to load workspace: {}", e));
+            return Err(e);
+        }
     };
 
-    handleCommand(&mut workspace, args.action_context)?;
+    let res = handleCommand(&mut workspace, args.action_context, output);
 
-    workspace.save()?;
+    // whatever went through before a failure is kept
+    if !read_only {
+        if let Err(e) = workspace.save() {
+            PrintFailure(output, format!("Failed to save workspace: {}", e));
+            return Err(e);
+        }
+    }
 
-    Ok(())
+    res
 }
 
-fn handleCommand(workspace: &mut Workspace, args: ActionContext) -> Result<(), std::io::Error> {
+/// Reports failures itself, the returned error only decides the exit code
+fn handleCommand(workspace: &mut Workspace, args: ActionContext, output: OutputFormat) -> Result<(), ModderError> {
     match args {
         ActionContext::Init(_) => {
             println!("You have already initialized a workspace.");
+            return Err(ModderError::WorkspaceExists);
         }
         ActionContext::Branch(branch) => match branch.action {
             branches::BranchAction::Switch(value) => {
-                let res = workspace.switch_branch(&value.branch);
+                // a bare branch switches to its latest version, anything else pins the version
+                let (target, version) = match workspace.resolve_target(&value.branch) {
+                    Ok(t) => t,
+                    Err(e) => {
+                        println!("Failed to switch branch: {}", e);
+                        return Err(e);
+                    }
+                };
+
+                let unsaved = (workspace.info.current_branch != Some(target.clone()) || version.is_some())
+                    && match workspace.status() {
+                        Ok(status) => !status.changes.is_empty(),
+                        Err(_) => false,
+                    };
+
+                if unsaved && value.save_first {
+                    let message = format!("Saved before switching to {}", target);
+
+                    match workspace.save_current_state(Some(message), vec![]) {
+                        Ok(version) => {
+                            println!("Saved unsaved changes as version {}.", version);
+                        }
+                        Err(e) => {
+                            println!("Failed to save workspace, not switching: {}", e);
+                            return Err(e);
+                        }
+                    }
+                }
+
+                if unsaved && value.stash {
+                    let message = format!("Stashed before switching to {}", target);
+
+                    match workspace.stash_push(Some(message)) {
+                        Ok(count) => {
+                            println!("Stashed {} unsaved change(s).", count);
+                        }
+                        Err(e) => {
+                            println!("Failed to stash changes, not switching: {}", e);
+                            return Err(e);
+                        }
+                    }
+                }
+
+                let res = workspace.switch_branch(&target, version, value.force);
 
                 match res {
                     Ok(e) => match e {
-                        SwitchResult::Success => {
-                            println!("Switched to branch: {}", value.branch);
-                        }
+                        SwitchResult::Success => match version {
+                            Some(version) => {
+                                println!("Switched to branch: {} at version {}", target, version);
+                            }
+                            None => {
+                                println!("Switched to branch: {}", target);
+                            }
+                        },
                         SwitchResult::AlreadtInBranch => {
-                            println!("Already in branch: {}", value.branch);
+                            println!("Already in branch: {}", target);
                         }
                         SwitchResult::NoFileMove => {
-                            println!("Switched to branch: {}", value.branch);
+                            println!("Switched to branch: {}", target);
                             println!(
                                 "No files were moved to the src folder as the branch is empty."
                             );
                         }
-                    },
-                    Err(e) => match e.kind() {
-                        std::io::ErrorKind::NotFound => {
-                            println!("Branch {} not found.", value.branch);
-                        }
-                        _ => {
-                            println!("Failed to switch branch: {}", e);
+                        SwitchResult::UnsavedChanges(count) => {
+                            let message = format!("src has {} unsaved change(s), not switching.", count);
+
+                            println!("{}", message);
+                            println!("Use --save-first to save them, --stash to stash them or --force to discard them.");
+                            return Err(ModderError::DirtySrc(message));
                         }
                     },
+                    Err(e) => {
+                        println!("Failed to switch branch: {}", e);
+                        return Err(e);
+                    }
                 }
             }
             branches::BranchAction::Create(value) => {
-                let branch = branch::Branch::new(value.branch.clone(), "New branch".to_string(), 1);
-                let res = workspace.add_branch(branch);
+                let description = value.description.unwrap_or("New branch".to_string());
+                let mut branch = branch::Branch::new(value.branch.clone(), description, 1);
+                branch.parent = workspace.fork_point();
+
+                let res = match &value.from {
+                    Some(from) => match workspace.resolve_revision(from) {
+                        Ok((from_branch, from_version)) => {
+                            workspace.add_branch_from(branch, (&from_branch, from_version))
+                        }
+                        Err(e) => Err(e),
+                    },
+                    None => workspace.add_branch(branch),
+                };
 
                 match res {
                     Ok(_) => {
@@ -111,7 +235,10 @@ fn handleCommand(workspace: &mut Workspace, args: ActionContext) -> Result<(), s
                         if value.swap {
                             let switchCommand =
                                 branches::BranchAction::Switch(branches::SwitchBranch {
-                                    branch: value.branch,
+                                    branch: Revision::branch(value.branch),
+                                    force: false,
+                                    save_first: false,
+                                    stash: false,
                                 });
 
                             handleCommand(
@@ -119,43 +246,530 @@ fn handleCommand(workspace: &mut Workspace, args: ActionContext) -> Result<(), s
                                 ActionContext::Branch(branches::BranchComand {
                                     action: switchCommand,
                                 }),
+                                OutputFormat::Human,
                             )?;
 
                             if value.save {
-                                handleCommand(workspace, ActionContext::Save)?;
+                                handleCommand(
+                                    workspace,
+                                    ActionContext::Save(args::SaveCommand {
+                                        message: None,
+                                        tags: vec![],
+                                    }),
+                                    OutputFormat::Human,
+                                )?;
                             }
                         }
                     }
                     Err(e) => {
                         println!("Failed to create branch: {}", e);
+                        return Err(e);
                     }
                 }
             }
             branches::BranchAction::Delete(value) => {
-                let res = workspace.remove_branch_by_name(&value.value);
+                // checked before asking so a typo doesn't get a question
+                let versions = match workspace.find_branch(&value.branch) {
+                    Some(b) => b.latest_version().unwrap_or(0),
+                    None => {
+                        let e = ModderError::BranchNotFound(value.branch.clone());
+                        println!("Failed to delete branch: {}", e);
+                        return Err(e);
+                    }
+                };
+
+                let (tags, stashes) = match workspace.branch_references(&value.branch) {
+                    Ok(r) => r,
+                    Err(e) => {
+                        println!("Failed to delete branch: {}", e);
+                        return Err(e);
+                    }
+                };
+
+                if !value.force && workspace.info.current_branch.as_deref() == Some(value.branch.as_str()) {
+                    let e = ModderError::CurrentBranch(value.branch.clone());
+                    println!("Failed to delete branch: {}", e);
+                    return Err(e);
+                }
+
+                if !tags.is_empty() {
+                    let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+                    println!(
+                        "Tags pointing at {}: {}, they can't be used until it is undeleted.",
+                        value.branch,
+                        names.join(", ")
+                    );
+                }
+
+                if !stashes.is_empty() {
+                    let indexes: Vec<String> = stashes.iter().map(|i| i.to_string()).collect();
+                    println!(
+                        "Stashes made on {}: {}, they can still be popped.",
+                        value.branch,
+                        indexes.join(", ")
+                    );
+                }
+
+                if !value.yes {
+                    let question = format!(
+                        "Delete branch {} with {} version(s)?",
+                        value.branch, versions
+                    );
+
+                    if !askConfirmation(&question) {
+                        println!("Branch {} was not deleted.", value.branch);
+                        return Err(ModderError::Cancelled);
+                    }
+                }
+
+                let res = workspace.remove_branch_by_name(&value.branch, value.force);
 
                 match res {
                     Ok(_) => {
-                        println!("Branch {} deleted.", value.value);
+                        println!("Branch {} moved to the trash.", value.branch);
+                        println!("Use 'branch undelete {}' to bring it back.", value.branch);
                     }
                     Err(e) => {
                         println!("Failed to delete branch: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            branches::BranchAction::Undelete(value) => {
+                let res = workspace.undelete_branch(&value.value);
+
+                match res {
+                    Ok(_) => {
+                        println!("Branch {} restored from the trash.", value.value);
+                    }
+                    Err(e) => {
+                        println!("Failed to undelete branch: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            branches::BranchAction::List => match output {
+                OutputFormat::Human => ListBranches(workspace)?,
+                OutputFormat::Json => PrintJson(&BranchListJson {
+                    info: &workspace.info,
+                    branches: &workspace.branches,
+                })?,
+            },
+            branches::BranchAction::Graph => {
+                PrintBranchGraph(workspace);
+            }
+            branches::BranchAction::Rename(value) => {
+                let res = workspace.rename_branch(&value.branch, &value.new_name);
+
+                match res {
+                    Ok(_) => {
+                        println!("Branch {} renamed to {}.", value.branch, value.new_name);
+                    }
+                    Err(e) => {
+                        println!("Failed to rename branch: {}", e);
+                        return Err(e);
                     }
                 }
             }
-            branches::BranchAction::List => {
-                ListBranches(&workspace)?;
+            branches::BranchAction::Describe(value) => {
+                let res = workspace.describe_branch(&value.branch, value.description);
+
+                match res {
+                    Ok(_) => {
+                        println!("Branch {} description updated.", value.branch);
+                    }
+                    Err(e) => {
+                        println!("Failed to describe branch: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            branches::BranchAction::Merge(value) => {
+                let res = workspace.merge_branch(&value.branch, &value.ours, &value.theirs);
+
+                match res {
+                    Ok(MergeResult::Merged {
+                        base,
+                        updated,
+                        removed,
+                    }) => {
+                        if let Some((branch, version)) = base {
+                            println!("Merging from common version {}@{}.", branch, version);
+                        }
+
+                        for file in &updated {
+                            println!("~ {}", file);
+                        }
+
+                        for file in &removed {
+                            println!("- {}", file);
+                        }
+
+                        println!(
+                            "Merged {} into src ({} file(s) changed), save to record the merge.",
+                            value.branch,
+                            updated.len() + removed.len()
+                        );
+                    }
+                    Ok(MergeResult::Conflicts(conflicts)) => {
+                        println!("Both branches changed these files:");
+
+                        for file in &conflicts {
+                            println!("! {}", file);
+                        }
+
+                        println!("Nothing was merged, use --ours or --theirs to pick a side.");
+                        return Err(ModderError::Conflicts(conflicts.len()));
+                    }
+                    Err(e) => {
+                        println!("Failed to merge branch: {}", e);
+                        return Err(e);
+                    }
+                }
             }
         },
-        ActionContext::Save => {
-            let res = workspace.save_current_state();
+        ActionContext::Save(value) => {
+            let res = workspace.save_current_state(value.message, value.tags);
+
+            match res {
+                Ok(version) => match output {
+                    OutputFormat::Human => println!("Workspace saved as version {}.", version),
+                    OutputFormat::Json => PrintJson(&SaveJson {
+                        branch: workspace.info.current_branch.as_deref().unwrap_or_default(),
+                        version,
+                    })?,
+                },
+                Err(e) => {
+                    PrintFailure(output, format!("Failed to save workspace: {}", e));
+                    return Err(e);
+                }
+            }
+        }
+        ActionContext::Restore(value) => {
+            let mut revision = value.branch.unwrap_or_default();
+
+            if let Some(version) = value.version {
+                revision.version = VersionSpec::Number(version);
+            }
+
+            let (branch, version) = match workspace.resolve_revision(&revision) {
+                Ok(r) => r,
+                Err(e) => {
+                    println!("Failed to restore: {}", e);
+                    return Err(e);
+                }
+            };
+
+            if !value.files.is_empty() {
+                let res = workspace.restore_files(&branch, Some(version), &value.files);
+
+                match res {
+                    Ok((version, files)) => {
+                        for file in &files {
+                            println!("• {}", file.display());
+                        }
+
+                        println!(
+                            "Restored {} file(s) from version {} of branch {}.",
+                            files.len(),
+                            version,
+                            branch
+                        );
+                    }
+                    Err(e) => {
+                        println!("Failed to restore files: {}", e);
+                        return Err(e);
+                    }
+                }
+
+                return Ok(());
+            }
+
+            let res = workspace.restore_version(&branch, Some(version), value.force);
+
+            match res {
+                Ok(version) => {
+                    println!("Restored branch {} to version {}.", branch, version);
+                }
+                Err(e) => {
+                    println!("Failed to restore branch: {}", e);
+                    return Err(e);
+                }
+            }
+        }
+        ActionContext::Log(value) => {
+            // a revision with a version shows the history up to that version
+            let (branch, version) = match workspace.resolve_target(&value.branch.unwrap_or_default()) {
+                Ok(t) => t,
+                Err(e) => {
+                    PrintFailure(output, format!("Failed to read history: {}", e));
+                    return Err(e);
+                }
+            };
+
+            match workspace.history(&branch) {
+                Ok(mut history) => {
+                    history.retain(|l| version.is_none_or(|v| l.version <= v));
+
+                    match output {
+                        OutputFormat::Human => PrintHistory(&branch, &history, value.limit),
+                        OutputFormat::Json => PrintJson(&LogJson {
+                            branch: &branch,
+                            versions: &history[..value.limit.unwrap_or(history.len()).min(history.len())],
+                        })?,
+                    }
+                }
+                Err(e) => {
+                    PrintFailure(output, format!("Failed to read history: {}", e));
+                    return Err(e);
+                }
+            }
+        }
+        ActionContext::Diff(value) => {
+            let from = workspace.resolve_revision(&value.from.unwrap_or_default());
+
+            let from = match from {
+                Ok(f) => f,
+                Err(e) => {
+                    PrintFailure(output, format!("Failed to diff: {}", e));
+                    return Err(e);
+                }
+            };
+
+            let to = match value.to.map(|to| workspace.resolve_revision(&to)) {
+                Some(Ok(t)) => Some(t),
+                Some(Err(e)) => {
+                    PrintFailure(output, format!("Failed to diff: {}", e));
+                    return Err(e);
+                }
+                None => None,
+            };
+
+            let res = workspace.diff(
+                (&from.0, from.1),
+                to.as_ref().map(|(branch, version)| (branch.as_str(), *version)),
+            );
+
+            let to_name = match &to {
+                Some((branch, version)) => format!("{}@{}", branch, version),
+                None => "src".to_string(),
+            };
 
             match res {
+                Ok(changes) => match output {
+                    OutputFormat::Human => {
+                        println!("Changes from {}@{} to {}:", from.0, from.1, to_name);
+                        PrintChanges(&changes);
+                    }
+                    OutputFormat::Json => PrintJson(&DiffJson {
+                        from: RevisionJson {
+                            branch: &from.0,
+                            version: from.1,
+                        },
+                        to: to.as_ref().map(|(branch, version)| RevisionJson {
+                            branch,
+                            version: *version,
+                        }),
+                        changes: &changes,
+                    })?,
+                },
+                Err(e) => {
+                    PrintFailure(output, format!("Failed to diff: {}", e));
+                    return Err(e);
+                }
+            }
+        }
+        ActionContext::Status => match workspace.status() {
+            Ok(status) => match output {
+                OutputFormat::Human => PrintStatus(&status),
+                OutputFormat::Json => PrintJson(&status)?,
+            },
+            Err(e) => {
+                PrintFailure(output, format!("Failed to get status: {}", e));
+                return Err(e);
+            }
+        },
+        ActionContext::Stash(stash) => match stash.action {
+            args::stash::StashAction::Push(value) => match workspace.stash_push(value.message) {
+                Ok(count) => {
+                    println!("Stashed {} change(s).", count);
+                }
+                Err(e) => {
+                    println!("Failed to stash changes: {}", e);
+                    return Err(e);
+                }
+            },
+            args::stash::StashAction::List => match workspace.load_stashes() {
+                Ok(stashes) => {
+                    PrintStashes(&stashes);
+                }
+                Err(e) => {
+                    println!("Failed to read stashes: {}", e);
+                    return Err(e);
+                }
+            },
+            args::stash::StashAction::Pop(value) => match workspace.stash_pop(value.index) {
+                Ok(stash) => {
+                    println!("Applied {} stashed change(s) to src.", stash.len());
+                }
+                Err(e) => {
+                    println!("Failed to pop stash: {}", e);
+                    return Err(e);
+                }
+            },
+            args::stash::StashAction::Drop(value) => match workspace.stash_drop(value.index) {
                 Ok(_) => {
-                    println!("Workspace saved.");
+                    println!("Dropped stash {}.", value.index);
+                }
+                Err(e) => {
+                    println!("Failed to drop stash: {}", e);
+                    return Err(e);
+                }
+            },
+        },
+        ActionContext::Pick(value) => {
+            let res = workspace
+                .resolve_revision(&value.from)
+                .and_then(|(branch, version)| {
+                    let picked = workspace.pick_files(&branch, version, &value.files)?;
+                    Ok((branch, version, picked))
+                });
+
+            match res {
+                Ok((branch, version, (added, overwritten))) => {
+                    for file in &added {
+                        println!("+ {}", file.display());
+                    }
+
+                    for file in &overwritten {
+                        println!("~ {} (overwritten)", file.display());
+                    }
+
+                    println!(
+                        "Picked {} file(s) from {}@{}.",
+                        added.len() + overwritten.len(),
+                        branch,
+                        version
+                    );
+                }
+                Err(e) => {
+                    println!("Failed to pick files: {}", e);
+                    return Err(e);
+                }
+            }
+        }
+        ActionContext::Trash(trash) => match trash.action {
+            args::trash::TrashAction::List => match workspace.load_trash() {
+                Ok(trash) => {
+                    println!("Trash:");
+
+                    for trashed in &trash {
+                        println!(
+                            "• {} (v.{}) deleted {}",
+                            trashed.branch.name,
+                            trashed.branch.version,
+                            trashed.deleted.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                        );
+                    }
+                }
+                Err(e) => {
+                    println!("Failed to read the trash: {}", e);
+                    return Err(e);
+                }
+            },
+            args::trash::TrashAction::Empty(value) => {
+                if !value.yes && !askConfirmation("Permanently remove every branch in the trash?") {
+                    println!("The trash was not emptied.");
+                    return Err(ModderError::Cancelled);
+                }
+
+                match workspace.empty_trash() {
+                    Ok(count) => {
+                        println!("Removed {} branch(es) from the trash.", count);
+                    }
+                    Err(e) => {
+                        println!("Failed to empty the trash: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+        },
+        ActionContext::Prune(value) => {
+            let branches = if value.all {
+                workspace.branches.iter().map(|b| b.name.clone()).collect()
+            } else {
+                match value.branch.or(workspace.info.current_branch.clone()) {
+                    Some(b) => vec![b],
+                    None => {
+                        println!("No branch selected, specify the branch to prune.");
+                        return Err(ModderError::NoBranchSelected);
+                    }
+                }
+            };
+
+            let policy = PrunePolicy {
+                keep_last: value.keep_last,
+                keep_tagged: value.keep_tagged,
+                older_than: value.older_than,
+                keep_daily: value.daily,
+            };
+
+            match workspace.prune(&branches, &policy, value.dry_run) {
+                Ok(report) => {
+                    PrintPruneReport(&report, value.dry_run);
+                }
+                Err(e) => {
+                    println!("Failed to prune: {}", e);
+                    return Err(e);
+                }
+            }
+        }
+        ActionContext::Tag(value) => {
+            let name = match value.name {
+                Some(n) => n,
+                None => {
+                    match workspace.load_tags() {
+                        Ok(tags) => {
+                            PrintTags(workspace, &tags);
+                        }
+                        Err(e) => {
+                            println!("Failed to read tags: {}", e);
+                            return Err(e);
+                        }
+                    }
+
+                    return Ok(());
+                }
+            };
+
+            if value.delete {
+                match workspace.remove_tag(&name) {
+                    Ok(tag) => {
+                        println!("Tag {} removed from {}@{}.", tag.name, tag.branch, tag.version);
+                    }
+                    Err(e) => {
+                        println!("Failed to remove tag: {}", e);
+                        return Err(e);
+                    }
+                }
+
+                return Ok(());
+            }
+
+            let res = workspace
+                .resolve_revision(&value.target.unwrap_or_default())
+                .and_then(|(branch, version)| {
+                    workspace.add_tag(&name, &branch, version)?;
+                    Ok((branch, version))
+                });
+
+            match res {
+                Ok((branch, version)) => {
+                    println!("Tagged {}@{} as {}.", branch, version, name);
                 }
                 Err(e) => {
-                    println!("Failed to save workspace: {}", e);
+                    println!("Failed to tag version: {}", e);
+                    return Err(e);
                 }
             }
         }
@@ -164,6 +778,17 @@ fn handleCommand(workspace: &mut Workspace, args: ActionContext) -> Result<(), s
     Ok(())
 }
 
+fn askConfirmation(question: &str) -> bool {
+    println!("{} [y/N]", question);
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .expect("Failed to read line");
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
 fn getModInfoFromUser() -> mod_info::ModInfo {
     // Get the mod info from the user
     // Get Mod Name
@@ -190,7 +815,60 @@ fn getModInfoFromUser() -> mod_info::ModInfo {
         .expect("Failed to read line");
     let description = description.trim().to_string();
 
-    return ModInfo::new(name, author, description, Some("main".to_string()));
+    ModInfo::new(name, author, description, Some("main".to_string()))
+}
+
+/// What `branch list` prints with `--output json`
+#[derive(Serialize)]
+struct BranchListJson<'a> {
+    info: &'a ModInfo,
+    branches: &'a [branch::Branch],
+}
+
+#[derive(Serialize)]
+struct SaveJson<'a> {
+    branch: &'a str,
+    version: i32,
+}
+
+#[derive(Serialize)]
+struct LogJson<'a> {
+    branch: &'a str,
+    versions: &'a [VersionLog],
+}
+
+#[derive(Serialize)]
+struct RevisionJson<'a> {
+    branch: &'a str,
+    version: i32,
+}
+
+/// `to` is null when the diff is against src
+#[derive(Serialize)]
+struct DiffJson<'a> {
+    from: RevisionJson<'a>,
+    to: Option<RevisionJson<'a>>,
+    changes: &'a FileChanges,
+}
+
+#[derive(Serialize)]
+struct ErrorJson {
+    error: String,
+    code: u8,
+}
+
+/// In json mode stdout only holds the json, the message goes to stderr
+fn PrintFailure(output: OutputFormat, message: String) {
+    match output {
+        OutputFormat::Human => println!("{}", message),
+        OutputFormat::Json => eprintln!("{}", message),
+    }
+}
+
+fn PrintJson<T: Serialize>(value: &T) -> Result<(), ModderError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+
+    Ok(())
 }
 
 fn ListBranches(workspace: &Workspace) -> Result<(), std::io::Error> {
@@ -206,3 +884,214 @@ fn ListBranches(workspace: &Workspace) -> Result<(), std::io::Error> {
 
     Ok(())
 }
+
+fn PrintHistory(branch: &str, history: &[VersionLog], limit: Option<usize>) {
+    println!("History of branch {}:", branch);
+
+    for entry in history.iter().take(limit.unwrap_or(history.len())) {
+        let date = match entry.created {
+            Some(d) => d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string(),
+            None => "unknown date".to_string(),
+        };
+
+        let tags = if entry.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", entry.tags.join(", "))
+        };
+
+        println!(
+            "• v.{} {} ({} files changed){}: {}",
+            entry.version,
+            date,
+            entry.files_changed,
+            tags,
+            entry.message.as_deref().unwrap_or("No message")
+        );
+    }
+}
+
+fn PrintChanges(changes: &FileChanges) {
+    if changes.is_empty() {
+        println!("No changes.");
+        return;
+    }
+
+    for file in &changes.added {
+        println!("+ {}", file);
+    }
+
+    for file in &changes.removed {
+        println!("- {}", file);
+    }
+
+    for file in &changes.modified {
+        println!("~ {}", file);
+    }
+
+    println!("{} file(s) changed.", changes.len());
+}
+
+fn PrintStatus(status: &Status) {
+    println!("On branch: {}", status.branch);
+
+    match (status.version, &status.base) {
+        (Some(version), _) => println!("Latest version: {}", version),
+        (None, Some(base)) => println!("No versions saved yet, created from {}@{}.", base.branch, base.version),
+        (None, None) => println!("No versions saved yet."),
+    }
+
+    if status.changes.is_empty() {
+        match &status.base {
+            Some(base) => println!("Nothing to save, src matches {}@{}.", base.branch, base.version),
+            None => println!("Nothing to save, src is empty."),
+        }
+
+        return;
+    }
+
+    println!("Unsaved changes:");
+
+    for file in &status.changes.added {
+        println!("  new:      {}", file);
+    }
+
+    for file in &status.changes.modified {
+        println!("  modified: {}", file);
+    }
+
+    for file in &status.changes.removed {
+        println!("  deleted:  {}", file);
+    }
+}
+
+fn PrintStashes(stashes: &[Stash]) {
+    println!("Stashes:");
+
+    for (index, stash) in stashes.iter().enumerate() {
+        let base = match stash.version {
+            Some(version) => format!("{}@{}", stash.branch, version),
+            None => stash.branch.clone(),
+        };
+
+        println!(
+            "• {} on {} ({} changes): {}",
+            index,
+            base,
+            stash.len(),
+            stash.message.as_deref().unwrap_or("No message")
+        );
+    }
+}
+
+fn PrintBranchGraph(workspace: &Workspace) {
+    println!("Branches:");
+
+    // branches whose parent is gone are shown as roots
+    let roots = workspace.branches.iter().filter(|b| match &b.parent {
+        Some(parent) => workspace.find_branch(&parent.branch).is_none(),
+        None => true,
+    });
+
+    for branch in roots {
+        PrintBranchNode(workspace, branch, "", "");
+    }
+}
+
+fn PrintBranchNode(workspace: &Workspace, branch: &branch::Branch, prefix: &str, child_prefix: &str) {
+    let versions = match branch.latest_version() {
+        Some(1) => "v.1".to_string(),
+        Some(latest) => format!("v.1-{}", latest),
+        None => "no versions".to_string(),
+    };
+
+    let fork = match &branch.parent {
+        Some(parent) if parent.version > 0 => format!(" from {}@{}", parent.branch, parent.version),
+        Some(parent) => format!(" from {}", parent.branch),
+        None => String::new(),
+    };
+
+    let current = if workspace.info.current_branch.as_deref() == Some(&branch.name) {
+        " *"
+    } else {
+        ""
+    };
+
+    println!("{}{} ({}){}{}", prefix, branch.name, versions, fork, current);
+
+    let children = workspace.child_branches(&branch.name);
+
+    for (index, child) in children.iter().enumerate() {
+        let last = index == children.len() - 1;
+
+        let (branch_prefix, next_prefix) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        PrintBranchNode(
+            workspace,
+            child,
+            &format!("{}{}", child_prefix, branch_prefix),
+            &format!("{}{}", child_prefix, next_prefix),
+        );
+    }
+}
+
+fn PrintPruneReport(report: &PruneReport, dry_run: bool) {
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+
+    for (branch, version) in &report.versions {
+        println!("- {}@{}", branch, version);
+    }
+
+    if !report.tags.is_empty() {
+        println!("{} the tags of these versions, use --keep-tagged to keep them:", verb);
+
+        for tag in &report.tags {
+            println!("- {} -> {}@{}", tag.name, tag.branch, tag.version);
+        }
+    }
+
+    println!(
+        "{} {} version(s), {} tag(s) and {} unused stored file(s), reclaiming {}.",
+        verb,
+        report.versions.len(),
+        report.tags.len(),
+        report.objects,
+        FormatBytes(report.bytes)
+    );
+}
+
+fn FormatBytes(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+fn PrintTags(workspace: &Workspace, tags: &[Tag]) {
+    println!("Tags:");
+
+    for tag in tags {
+        // the branch may be in the trash
+        let deleted = if workspace.find_branch(&tag.branch).is_none() {
+            " (branch deleted)"
+        } else {
+            ""
+        };
+
+        println!("• {} -> {}@{}{}", tag.name, tag.branch, tag.version, deleted);
+    }
+}
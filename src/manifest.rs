@@ -0,0 +1,64 @@
+use std::{collections::BTreeMap, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ModderError;
+
+/// A single saved file, keyed in the manifest by its path relative to src
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub hash: String,
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+}
+
+impl ManifestEntry {
+    pub fn new(hash: String, metadata: &std::fs::Metadata) -> Result<ManifestEntry, std::io::Error> {
+        Ok(ManifestEntry {
+            hash,
+            size: metadata.len(),
+            modified: metadata.modified()?.into(),
+        })
+    }
+}
+
+/// The content of a saved version, mapping paths relative to src to their hash in the object store
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: i32,
+    pub created: DateTime<Utc>,
+    pub message: Option<String>,
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn new(version: i32, message: Option<String>) -> Manifest {
+        Manifest {
+            version,
+            created: Utc::now(),
+            message,
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_file(&mut self, relative: String, hash: String, metadata: &std::fs::Metadata) -> Result<(), std::io::Error> {
+        self.files.insert(relative, ManifestEntry::new(hash, metadata)?);
+
+        Ok(())
+    }
+
+    pub fn load(manifest_file: &Path) -> Result<Manifest, ModderError> {
+        let manifest = std::fs::read_to_string(manifest_file)?;
+        let manifest = serde_json::from_str(&manifest)?;
+
+        Ok(manifest)
+    }
+
+    pub fn save(&self, manifest_file: &Path) -> Result<(), std::io::Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(manifest_file, json)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::ModderError;
+
+/// `.branches` -> `.branches.bak`, next to the original
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(extension);
+
+    path.with_file_name(name)
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling(path, ".bak")
+}
+
+/// Replaces the file in one step so a crash leaves either the old or the new content, never half of it
+pub fn replace(path: &Path, content: &str) -> Result<(), std::io::Error> {
+    let temp = sibling(path, ".tmp");
+
+    let mut file = File::create(&temp)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::rename(&temp, path)?;
+
+    // the rename itself only survives a crash once the folder is synced,
+    // folders can't be opened on every platform so this is best effort
+    if let Some(Ok(folder)) = path.parent().map(File::open) {
+        _ = folder.sync_all();
+    }
+
+    Ok(())
+}
+
+/// Writes metadata as json, keeping the previous version as `.bak`
+pub fn write<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), std::io::Error> {
+    let json = serde_json::to_string_pretty(value)?;
+
+    if path.exists() {
+        std::fs::copy(path, backup_path(path))?;
+    }
+
+    replace(path, &json)
+}
+
+/// Reads json metadata, recovering it from the `.bak` copy when the file is missing or broken
+pub fn read<T: DeserializeOwned>(path: &Path) -> Result<T, ModderError> {
+    let error = match parse(path) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+
+    let backup = backup_path(path);
+
+    let content = match std::fs::read_to_string(&backup) {
+        Ok(c) => c,
+        Err(_) => return Err(error),
+    };
+
+    let value = match serde_json::from_str(&content) {
+        Ok(v) => v,
+        Err(_) => return Err(error),
+    };
+
+    eprintln!(
+        "{} could not be read ({}), recovered the previous copy from {}.",
+        path.display(),
+        error,
+        backup.display()
+    );
+
+    // put the good copy back without touching the backup
+    replace(path, &content)?;
+
+    Ok(value)
+}
+
+fn parse<T: DeserializeOwned>(path: &Path) -> Result<T, ModderError> {
+    let content = std::fs::read_to_string(path)?;
+
+    Ok(serde_json::from_str(&content)?)
+}
@@ -22,11 +22,8 @@ impl ModInfo {
         }
     }
 
-    pub fn load_info(info_file: &std::path::PathBuf) -> Result<ModInfo, std::io::Error> {
-        let info = std::fs::read_to_string(info_file)?;
-        let info: ModInfo = serde_json::from_str(&info)?;
-
-        Ok(info)
+    pub fn load_info(info_file: &std::path::Path) -> Result<ModInfo, crate::error::ModderError> {
+        crate::metadata::read(info_file)
     }
 
     
@@ -0,0 +1,52 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+/// Content addressed storage for saved files, every file is stored once under its hash
+#[derive(Debug)]
+pub struct ObjectStore {
+    pub folder: PathBuf,
+}
+
+impl ObjectStore {
+    pub fn new(folder: PathBuf) -> ObjectStore {
+        ObjectStore { folder }
+    }
+
+    pub fn hash_file(file: &Path) -> Result<String, std::io::Error> {
+        let mut hasher = Sha256::new();
+        let mut reader = std::fs::File::open(file)?;
+
+        std::io::copy(&mut reader, &mut hasher)?;
+        hasher.flush()?;
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    pub fn object_path(&self, hash: &str) -> PathBuf {
+        // split on the first two characters to avoid one huge folder
+        self.folder.join(&hash[..2]).join(hash)
+    }
+
+    /// Adds the file to the store if its content isn't there yet and returns its hash
+    pub fn store(&self, file: &Path) -> Result<String, std::io::Error> {
+        let hash = ObjectStore::hash_file(file)?;
+        let object = self.object_path(&hash);
+
+        if object.exists() {
+            return Ok(hash);
+        }
+
+        std::fs::create_dir_all(object.parent().unwrap())?;
+
+        // copy next to the object first so a failed copy never leaves a broken object behind
+        let temp = object.with_extension("tmp");
+        std::fs::copy(file, &temp)?;
+        std::fs::rename(&temp, &object)?;
+
+        Ok(hash)
+    }
+}
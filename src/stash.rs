@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::ManifestEntry;
+
+/// Unsaved changes of src parked outside of the branch versions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stash {
+    pub branch: String,
+    pub version: Option<i32>,
+    pub created: DateTime<Utc>,
+    pub message: Option<String>,
+    /// New and modified files, stored in the object store
+    pub files: BTreeMap<String, ManifestEntry>,
+    pub removed: Vec<String>,
+}
+
+impl Stash {
+    pub fn new(branch: String, version: Option<i32>, message: Option<String>) -> Stash {
+        Stash {
+            branch,
+            version,
+            created: Utc::now(),
+            message,
+            files: BTreeMap::new(),
+            removed: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len() + self.removed.len()
+    }
+
+    /// Every path relative to src the stash changes
+    pub fn paths(&self) -> impl Iterator<Item = &String> {
+        self.files.keys().chain(self.removed.iter())
+    }
+}
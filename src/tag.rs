@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A stable name for a saved version that can be used anywhere a version is expected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    pub branch: String,
+    pub version: i32,
+    pub created: DateTime<Utc>,
+}
+
+impl Tag {
+    pub fn new(name: String, branch: String, version: i32) -> Tag {
+        Tag {
+            name,
+            branch,
+            version,
+            created: Utc::now(),
+        }
+    }
+}
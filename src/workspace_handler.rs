@@ -1,6 +1,30 @@
-use std::path::{Path, PathBuf};
-
-use crate::{branch::Branch, mod_info::ModInfo};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+mod prune;
+mod transaction;
+
+pub use prune::{PrunePolicy, PruneReport};
+
+use crate::{
+    args::revision::{Revision, VersionSpec},
+    branch::{self, Branch, BranchParent, MergeRecord, TrashedBranch},
+    diff::FileChanges,
+    error::{FileFailure, ModderError},
+    ignore::IgnorePatterns,
+    lock::WorkspaceLock,
+    manifest::{Manifest, ManifestEntry},
+    metadata,
+    mod_info::ModInfo,
+    object_store::ObjectStore,
+    stash::Stash,
+    tag::Tag,
+};
 
 // make custom error for empty branch folder
 #[derive(Debug)]
@@ -8,17 +32,48 @@ pub struct Workspace {
     pub root_folder: PathBuf,
     pub info: ModInfo,
     pub branches: Vec<Branch>,
-    pub ignore_files_pattern: Vec<String>,
+    pub ignore_files_pattern: IgnorePatterns,
+}
+
+/// A saved version as shown by the log command
+#[derive(Serialize)]
+pub struct VersionLog {
+    pub version: i32,
+    pub created: Option<DateTime<Utc>>,
+    pub message: Option<String>,
+    pub tags: Vec<String>,
+    pub files_changed: usize,
+}
+
+/// The state of src compared to the latest saved version of the current branch
+#[derive(Serialize)]
+pub struct Status {
+    pub branch: String,
+    pub version: Option<i32>,
+    /// The version src was compared against, the one the branch was created from
+    /// until it is saved for the first time. None when there is nothing to compare.
+    pub base: Option<BranchParent>,
+    pub changes: FileChanges,
+}
+
+pub enum MergeResult {
+    Merged {
+        base: Option<(String, i32)>,
+        updated: Vec<String>,
+        removed: Vec<String>,
+    },
+    Conflicts(Vec<String>),
 }
 
 pub enum SwitchResult {
     Success,
     AlreadtInBranch,
     NoFileMove,
+    UnsavedChanges(usize),
 }
 
 impl Workspace {
-    pub fn new(root_folder: PathBuf, info: ModInfo, branches: Vec<Branch>, ignore_files_pattern: Vec<String>) -> Workspace {
+    pub fn new(root_folder: PathBuf, info: ModInfo, branches: Vec<Branch>, ignore_files_pattern: IgnorePatterns) -> Workspace {
         Workspace {
             root_folder,
             info,
@@ -27,21 +82,22 @@ impl Workspace {
         }
     }
 
-    pub fn load_workspace() -> Result<Workspace, std::io::Error> {
+    /// Opens the workspace around the current folder
+    pub fn load_workspace(lock: Option<&WorkspaceLock>) -> Result<Workspace, ModderError> {
         // Load the workspace
-        println!("Load workspace");
+        eprintln!("Load workspace");
 
         let root_folder = Workspace::find_root_folder()?;
 
-        if root_folder == None {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Workspace not found.",
-            ));
+        if root_folder.is_none() {
+            return Err(ModderError::WorkspaceNotFound);
         }
 
-        let root_folder = root_folder.unwrap();
+        Workspace::open(root_folder.unwrap(), lock)
+    }
 
+    /// Loads the workspace in the given folder, finishing an interrupted operation when the lock is held
+    pub fn open(root_folder: PathBuf, lock: Option<&WorkspaceLock>) -> Result<Workspace, ModderError> {
         let info_file = root_folder.join(".info");
         let info = ModInfo::load_info(&info_file)?;
 
@@ -50,30 +106,40 @@ impl Workspace {
 
         let ignore_files_patterns = Workspace::load_ignore_patterns(&root_folder)?;
 
-        Ok(Workspace::new(root_folder, info, branches, ignore_files_patterns))
+        let mut workspace = Workspace::new(root_folder, info, branches, ignore_files_patterns);
+
+        if lock.is_some() {
+            workspace.recover()?;
+        }
+
+        Ok(workspace)
+    }
+
+    /// Takes the lock of the workspace around the current folder, `None` when there is no workspace
+    pub fn lock() -> Result<Option<WorkspaceLock>, ModderError> {
+        match Workspace::find_root_folder()? {
+            Some(root_folder) => Ok(Some(WorkspaceLock::acquire(&root_folder)?)),
+            None => Ok(None),
+        }
     }
 
-    fn load_ignore_patterns(root_folder: &Path) -> Result<Vec<String>, std::io::Error> {
+    fn load_ignore_patterns(root_folder: &Path) -> Result<IgnorePatterns, ModderError> {
         let ignore_file = root_folder.join(".ignore");
 
         if !ignore_file.exists() {
-            return Ok(vec![]);
+            return Ok(IgnorePatterns::default());
         }
 
         let ignore_file = std::fs::read_to_string(&ignore_file)?;
-        let ignore_file = ignore_file.lines().map(|l| l.to_string()).collect();
 
-        Ok(ignore_file)
+        IgnorePatterns::parse(&ignore_file)
     }
 
-    fn load_branches(branch_file: &PathBuf) -> Result<Vec<Branch>, std::io::Error> {
-        let branches = std::fs::read_to_string(&branch_file)?;
-        let branches = serde_json::from_str(&branches)?;
-
-        Ok(branches)
+    fn load_branches(branch_file: &Path) -> Result<Vec<Branch>, ModderError> {
+        metadata::read(branch_file)
     }
 
-    pub fn find_root_folder() -> Result<Option<PathBuf>, std::io::Error> {
+    pub fn find_root_folder() -> Result<Option<PathBuf>, ModderError> {
         // Find the root folder of the project
         let mut path = std::env::current_dir()?;
         let mut found = false;
@@ -102,12 +168,12 @@ impl Workspace {
             count += 1;
         }
 
-        return if found { Ok(Some(path)) } else { Ok(None) };
+        if found { Ok(Some(path)) } else { Ok(None) }
     }
 
-    pub fn init(root_folder: PathBuf, info: ModInfo) -> Result<Workspace, std::io::Error> {
+    pub fn init(root_folder: PathBuf, info: ModInfo) -> Result<Workspace, ModderError> {
         // Create a new workspace in the current directory
-        println!("Init");
+        eprintln!("Init");
 
         // check folder exists
         if !root_folder.exists() {
@@ -121,32 +187,29 @@ impl Workspace {
                 let entry = entry?;
 
                 if entry.file_name() == ".info" {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::AlreadyExists,
-                        "Workspace already exists.",
-                    ));
+                    return Err(ModderError::WorkspaceExists);
                 }
             }
         }
 
-        let src_folder = root_folder.join("src");
         // Create a new directory for src, branches, and publish
-        _ = std::fs::create_dir(src_folder);
+        let mut failures = vec![];
 
-        let publish_folder = root_folder.join("publish");
-        _ = std::fs::create_dir(publish_folder);
+        for folder in ["src", "publish", "branches", "branches/main"] {
+            let folder = root_folder.join(folder);
 
-        let branches_folder = root_folder.join("branches");
-        _ = std::fs::create_dir(&branches_folder);
+            if let Err(e) = std::fs::create_dir_all(&folder) {
+                failures.push(FileFailure::new(folder, e));
+            }
+        }
 
-        let main_branch_folder = branches_folder.join("main");
-        _ = std::fs::create_dir(main_branch_folder);
+        ModderError::check_files("create", failures)?;
 
         // Create a file to store the branches
         let branch = Branch::new("main".to_string(), "Main branch".to_string(), 1);
         let branches = vec![];
 
-        let mut workspace = Workspace::new(root_folder, info, branches, vec![]);
+        let mut workspace = Workspace::new(root_folder, info, branches, IgnorePatterns::default());
         workspace.add_branch(branch)?;
         workspace.save()?;
 
@@ -169,317 +232,1800 @@ impl Workspace {
         self.root_folder.join("branches/.branches")
     }
 
-    pub fn current_branch_folder_path(&self) -> Result<PathBuf, std::io::Error> {
-        let current_branch = match &self.info.current_branch {
-            Some(b) => b,
-            None => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "No Branches Selected.",
-                ))
-            }
-        };
+    pub fn trash_folder_path(&self) -> PathBuf {
+        self.root_folder.join("branches/.trash")
+    }
+
+    pub fn trash_path(&self) -> PathBuf {
+        self.root_folder.join("branches/.trash/.trashed")
+    }
+
+    pub fn tags_path(&self) -> PathBuf {
+        self.root_folder.join("branches/.tags")
+    }
 
-        Ok(self.root_folder.join("branches").join(current_branch))
+    pub fn stashes_path(&self) -> PathBuf {
+        self.root_folder.join("branches/.stashes")
     }
 
-    pub fn add_branch(&mut self, branch: Branch) -> Result<(), std::io::Error> {
+    pub fn add_branch(&mut self, branch: Branch) -> Result<(), ModderError> {
+        self.validate_branch_name(&branch.name)?;
+
         // check if branch already exists
 
         for b in &self.branches {
             if b.name == branch.name {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::AlreadyExists,
-                    "Branch already exists.",
-                ));
+                return Err(ModderError::BranchExists(branch.name.clone()));
             }
         }
 
         // create the branch folder
         let branch_folder = self.branches_folder_path().join(&branch.name);
-        _ = std::fs::create_dir(&branch_folder);
+        std::fs::create_dir_all(&branch_folder)?;
 
         self.branches.push(branch);
 
         Ok(())
     }
 
-    pub fn switch_branch(&mut self, name: &str) -> Result<SwitchResult, std::io::Error> {
-        // check if branch exists
-        if self.info.current_branch == Some(name.to_string()) {
-            return Ok(SwitchResult::AlreadtInBranch);
+    /// Renames a branch along with its folder and everything referring to it
+    pub fn rename_branch(&mut self, name: &str, new_name: &str) -> Result<(), ModderError> {
+        self.validate_branch_name(new_name)?;
+
+        if self.find_branch(name).is_none() {
+            return Err(ModderError::BranchNotFound(name.to_string()));
         }
 
-        let found = self.branches.iter().find(|b| b.name == name);
+        if self.find_branch(new_name).is_some() {
+            return Err(ModderError::BranchExists(new_name.to_string()));
+        }
 
+        let branch_folder = self.branches_folder_path().join(name);
+        let new_branch_folder = self.branches_folder_path().join(new_name);
 
-        let branch = match found {
+        let old_stashes = self.load_stashes()?;
+        let old_tags = self.load_tags()?;
+        let old_branches = self.branches.clone();
+        let old_current_branch = self.info.current_branch.clone();
+
+        let mut stashes = old_stashes.clone();
+
+        for stash in stashes.iter_mut().filter(|s| s.branch == name) {
+            stash.branch = new_name.to_string();
+        }
+
+        let mut tags = old_tags.clone();
+
+        for tag in tags.iter_mut().filter(|t| t.branch == name) {
+            tag.branch = new_name.to_string();
+        }
+
+        for branch in &mut self.branches {
+            if branch.name == name {
+                branch.name = new_name.to_string();
+            }
+
+            let sources = branch
+                .parent
+                .iter_mut()
+                .chain(branch.pending_merge.iter_mut())
+                .chain(branch.merges.iter_mut().map(|m| &mut m.source));
+
+            for source in sources.filter(|p| p.branch == name) {
+                source.branch = new_name.to_string();
+            }
+        }
+
+        if self.info.current_branch.as_deref() == Some(name) {
+            self.info.current_branch = Some(new_name.to_string());
+        }
+
+        // the metadata is written right away and the folder moved last, if any step
+        // fails everything goes back to the old name so the two never disagree
+        let renamed = self
+            .save()
+            .and_then(|_| self.save_stashes(&stashes))
+            .and_then(|_| self.save_tags(&tags))
+            .and_then(|_| {
+                if branch_folder.exists() {
+                    std::fs::rename(&branch_folder, &new_branch_folder)?;
+                }
+
+                Ok(())
+            });
+
+        if let Err(e) = renamed {
+            self.branches = old_branches;
+            self.info.current_branch = old_current_branch;
+
+            _ = self
+                .save()
+                .and_then(|_| self.save_stashes(&old_stashes))
+                .and_then(|_| self.save_tags(&old_tags));
+
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    pub fn describe_branch(&mut self, name: &str, description: String) -> Result<(), ModderError> {
+        let branch = match self.branches.iter_mut().find(|b| b.name == name) {
             Some(b) => b,
             None => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "Branch not found.",
-                ))
+                return Err(ModderError::BranchNotFound(name.to_string()))
             }
         };
 
-        self.info.current_branch = Some(branch.name.clone());
-
-        // copy the branch files to the src folder
-        let branch_folder = self.branches_folder_path().join(&branch.name);
+        branch.description = description;
 
-        // get the latest version of the branch
-        let latest_version = branch.version - 1;
+        Ok(())
+    }
 
-        let version_string = format!("{}", latest_version);
+    /// Merges the latest version of a branch into src, using the version both branches
+    /// share as a base. Files changed on both sides are conflicts unless they match
+    /// one of the `ours` or `theirs` patterns.
+    pub fn merge_branch(&mut self, source: &str, ours: &[String], theirs: &[String]) -> Result<MergeResult, ModderError> {
+        let status = self.status()?;
 
-        let version_folder = branch_folder.join(&version_string);
+        if status.branch == source {
+            return Err(ModderError::Invalid("Cannot merge a branch into itself.".to_string()));
+        }
 
-        // check if the version folder exists
-        if !version_folder.exists() {
-            return Ok(SwitchResult::NoFileMove);
+        // the result is written to src, so nothing unsaved can be in the way
+        if !status.changes.is_empty() {
+            return Err(ModderError::DirtySrc(
+                format!("src has {} unsaved change(s), save or stash them before merging.", status.changes.len()),
+            ));
         }
 
-        let src_folder = self.src_folder_path();
+        let source_version = match self.find_branch(source) {
+            Some(b) => match b.latest_version() {
+                Some(v) => v,
+                None => {
+                    return Err(ModderError::NoVersions(source.to_string()))
+                }
+            },
+            None => return Err(ModderError::BranchNotFound(source.to_string())),
+        };
 
-        // clear the src folder without deleting it
-        let src_files = std::fs::read_dir(&src_folder)?;
+        let ours_patterns = Workspace::compile_patterns(ours)?;
+        let theirs_patterns = Workspace::compile_patterns(theirs)?;
 
-        for file in src_files {
-            let file = file?;
-            let file_path = file.path();
+        let base = self.merge_base(&status.branch, source);
+
+        let base_files = match &base {
+            Some((branch, version)) if *version > 0 => self.version_hashes(branch, *version)?,
+            _ => BTreeMap::new(),
+        };
 
-            if file_path.is_file() {
-                _ = std::fs::remove_file(&file_path);
+        let our_files = match &status.base {
+            Some(base) => self.version_hashes(&base.branch, base.version)?,
+            None => BTreeMap::new(),
+        };
+
+        let their_files = self.version_hashes(source, source_version)?;
+
+        let mut paths: Vec<&String> = base_files.keys().chain(our_files.keys()).chain(their_files.keys()).collect();
+        paths.sort();
+        paths.dedup();
+
+        let mut take_theirs = vec![];
+        let mut conflicts = vec![];
+
+        for path in paths {
+            let base_hash = base_files.get(path);
+            let our_hash = our_files.get(path);
+            let their_hash = their_files.get(path);
+
+            if our_hash == their_hash || their_hash == base_hash {
+                continue;
+            }
+
+            if our_hash == base_hash {
+                take_theirs.push(path.clone());
+                continue;
             }
-            else if file_path.is_dir() {
-                _ = std::fs::remove_dir_all(&file_path);
+
+            // both sides changed the file
+            let relative = Path::new(path);
+
+            if Workspace::matches_any(&theirs_patterns, relative) {
+                take_theirs.push(path.clone());
+            } else if !Workspace::matches_any(&ours_patterns, relative) {
+                conflicts.push(path.clone());
             }
         }
 
-        Workspace::recurcive_copy(&version_folder, &src_folder, &None)?;
+        if !conflicts.is_empty() {
+            return Ok(MergeResult::Conflicts(conflicts));
+        }
 
-        Ok(SwitchResult::Success)
+        let src_folder = self.src_folder_path();
+        let (removed, updated): (Vec<String>, Vec<String>) =
+            take_theirs.into_iter().partition(|path| !their_files.contains_key(path));
+
+        let mut files = self.snapshot_files(source, source_version)?;
+        files.retain(|(_, relative)| updated.contains(&Workspace::relative_path_string(relative)));
+
+        Workspace::copy_files(&src_folder, &files)?;
+
+        for path in &removed {
+            std::fs::remove_file(src_folder.join(path))?;
+        }
+
+        // the next save records the merge so merging again starts from here
+        if let Some(branch) = self.branches.iter_mut().find(|b| b.name == status.branch) {
+            branch.pending_merge = Some(BranchParent {
+                branch: source.to_string(),
+                version: source_version,
+            });
+        }
+
+        Ok(MergeResult::Merged {
+            base,
+            updated,
+            removed,
+        })
     }
 
-    pub fn remove_branch_by_name(&mut self, name: &str) -> Result<(), std::io::Error> {
-        // check if branch exists
-        let found = self.branches.iter().find(|b| b.name == name);
+    /// Finds the latest version two branches have in common, from the last merge between
+    /// them or else by following where they were created from
+    pub fn merge_base(&self, first: &str, second: &str) -> Option<(String, i32)> {
+        if let Some(base) = self.last_merge(first, second) {
+            return Some(base);
+        }
 
-        let branch = match found {
-            Some(b) => b,
-            None => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "Branch not found.",
-                ))
+        let first = self.ancestry(first);
+        let second = self.ancestry(second);
+
+        for (branch, version) in &first {
+            if let Some((_, other_version)) = second.iter().find(|(b, _)| b == branch) {
+                return Some((branch.clone(), *version.min(other_version)));
             }
-        };
+        }
 
-        self.info.current_branch = None;
+        None
+    }
 
-        // remove the branch folder
-        let branch_folder = self.branches_folder_path().join(&branch.name);
-        _ = std::fs::remove_dir_all(&branch_folder);
+    /// The newest version of either branch that was merged into the other one
+    fn last_merge(&self, first: &str, second: &str) -> Option<(String, i32)> {
+        let merged_into = |into: &str, from: &str| -> Option<MergeRecord> {
+            let branch = self.find_branch(into)?;
 
-        // remove the branch from the branches list
-        self.branches.retain(|b| b.name != name);
+            branch.merges.iter().rev().find(|m| m.source.branch == from).cloned()
+        };
 
-        Ok(())
+        match (merged_into(first, second), merged_into(second, first)) {
+            // the later merge is the one whose source already contained the other merge
+            (Some(into_first), Some(into_second)) if into_second.source.version >= into_first.version => {
+                Some((first.to_string(), into_second.source.version))
+            }
+            (Some(into_first), _) => Some((second.to_string(), into_first.source.version)),
+            (None, Some(into_second)) => Some((first.to_string(), into_second.source.version)),
+            (None, None) => None,
+        }
     }
 
-    pub fn save_info(&self) -> Result<(), std::io::Error> {
-        let json = serde_json::to_string_pretty(&self.info)?;
-        _ = std::fs::write(self.info_path(), json)?;
+    /// The branch followed by its parents, each with the latest version that belongs to the line
+    fn ancestry(&self, name: &str) -> Vec<(String, i32)> {
+        let mut ancestry: Vec<(String, i32)> = vec![];
+        let mut current = self.find_branch(name);
+        let mut version = current.and_then(|b| b.latest_version()).unwrap_or(0);
 
-        Ok(())
-    }
+        while let Some(branch) = current {
+            // guard against a broken .branches file looping on itself
+            if ancestry.iter().any(|(b, _)| *b == branch.name) {
+                break;
+            }
 
-    pub fn save_branches(&self) -> Result<(), std::io::Error> {
-        let json = serde_json::to_string_pretty(&self.branches)?;
-        _ = std::fs::write(self.branches_path(), json)?;
+            ancestry.push((branch.name.clone(), version));
 
-        Ok(())
+            current = match &branch.parent {
+                Some(parent) => {
+                    version = parent.version;
+                    self.find_branch(&parent.branch)
+                }
+                None => None,
+            };
+        }
+
+        ancestry
     }
 
-    pub fn save(&self) -> Result<(), std::io::Error> {
-        self.save_info()?;
-        self.save_branches()?;
+    /// Where a new branch would fork from, the latest version of the current branch
+    pub fn fork_point(&self) -> Option<BranchParent> {
+        let branch = self.find_branch(self.info.current_branch.as_ref()?)?;
 
-        Ok(())
+        Some(BranchParent {
+            branch: branch.name.clone(),
+            version: branch.latest_version().unwrap_or(0),
+        })
     }
 
-    pub fn save_current_state(&mut self) -> Result<(), std::io::Error> {
-        // Save the current state of the mod to the current branch
-        let src_folder = self.src_folder_path();
-        let current_branch_folder = match self.current_branch_folder_path() {
-            Ok(p) => p,
-            Err(e) => return Err(e),
-        };
+    /// The branches created from the given branch
+    pub fn child_branches(&self, name: &str) -> Vec<&Branch> {
+        self.branches
+            .iter()
+            .filter(|b| b.parent.as_ref().is_some_and(|p| p.branch == name))
+            .collect()
+    }
 
-        let mut branch = None;
+    /// Creates a branch whose first version is a copy of an existing version, sharing its stored files
+    pub fn add_branch_from(&mut self, mut branch: Branch, from: (&str, i32)) -> Result<(), ModderError> {
+        let (from_branch, from_version) = from;
+        let message = Some(format!("Created from {}@{}", from_branch, from_version));
 
-        let current_branch = match &self.info.current_branch {
-            Some(b) => b,
-            None => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "No Branches Selected",
-                ))
-            }
-        };
+        let manifest_file = self.manifest_path(from_branch, from_version);
 
-        for b in &mut self.branches {
-            if b.name.eq(current_branch) {
-                branch = Some(b);
-                break;
+        let mut manifest = Manifest::new(1, message);
+
+        if manifest_file.exists() {
+            manifest.files = Manifest::load(&manifest_file)?.files;
+        } else {
+            // versions saved before the object store get their files stored now
+            let store = self.object_store();
+
+            for (stored, relative) in self.snapshot_files(from_branch, from_version)? {
+                let hash = store.store(&stored)?;
+                let relative = Workspace::relative_path_string(&relative);
+
+                manifest.add_file(relative, hash, &std::fs::metadata(&stored)?)?;
             }
         }
 
-        let branch = match branch {
+        branch.version = 2;
+        branch.parent = Some(BranchParent {
+            branch: from_branch.to_string(),
+            version: from_version,
+        });
+
+        let name = branch.name.clone();
+        self.add_branch(branch)?;
+
+        manifest.save(&self.manifest_path(&name, 1))?;
+
+        Ok(())
+    }
+
+    /// Switches to the latest version of a branch, or to the given version of it
+    pub fn switch_branch(&mut self, name: &str, version: Option<i32>, force: bool) -> Result<SwitchResult, ModderError> {
+        // check if branch exists
+        if self.info.current_branch == Some(name.to_string()) && version.is_none() {
+            return Ok(SwitchResult::AlreadtInBranch);
+        }
+
+        let found = self.branches.iter().find(|b| b.name == name);
+
+
+        let branch = match found {
             Some(b) => b,
             None => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "Current Branch does not exist in branches.",
-                ))
+                return Err(ModderError::BranchNotFound(name.to_string()))
             }
         };
 
-        let version_string = format!("{}", branch.version);
-        let new_branch_folder = current_branch_folder.join(&version_string);
+        // check if the branch was ever saved
+        if branch.latest_version().is_none() {
+            self.info.current_branch = Some(name.to_string());
+            return Ok(SwitchResult::NoFileMove);
+        }
 
-        branch.version += 1;
+        let version = self.resolve_version(name, version)?;
 
-        // ensure the branch folder exists
-        _ = std::fs::create_dir_all(&new_branch_folder);
+        // src is about to be replaced, make sure nothing unsaved is lost
+        if !force && self.info.current_branch.is_some() {
+            let status = self.status()?;
 
-        if self.info.top_files_only {
-            Workspace::top_level_copy(&src_folder, &new_branch_folder, &self.info.file_type)
-        } else {
-            Workspace::recurcive_copy(&src_folder, &new_branch_folder, &self.info.file_type)
+            if !status.changes.is_empty() {
+                return Ok(SwitchResult::UnsavedChanges(status.changes.len()));
+            }
         }
-    }
 
-    fn allow_copy(file: &PathBuf, allowed_file_type: &Option<String>) -> bool {
-        let allowed_file_type = match allowed_file_type {
-            Some(t) => t,
-            None => return true,
-        };
-        let file_name = file.file_name().unwrap().to_str().unwrap();
+        let files = self.snapshot_files(name, version)?;
 
-        file_name.ends_with(allowed_file_type)
-    }
+        self.commit_checkout(name, version, &files)?;
 
-    fn top_level_copy(
-        src: &PathBuf,
-        dest: &PathBuf,
-        fileType: &Option<String>,
-    ) -> Result<(), std::io::Error> {
-        // copy the files in the src folder to the branch folder
-        let srcFiles = std::fs::read_dir(&src)?;
+        Ok(SwitchResult::Success)
+    }
 
-        for file in srcFiles {
-            let file = file?;
-            let file_name = file.file_name();
-            let file_path = file.path();
+    /// Replaces src with a saved version and makes its branch the current one,
+    /// unsaved changes in src stop it unless forced
+    pub fn restore_version(&mut self, name: &str, version: Option<i32>, force: bool) -> Result<i32, ModderError> {
+        let version = self.resolve_version(name, version)?;
 
-            let new_file_path = dest.join(file_name);
+        if !force && self.info.current_branch.is_some() {
+            let status = self.status()?;
 
-            if file_path.is_file() && Workspace::allow_copy(&file_path, fileType) {
-                _ = std::fs::copy(file_path, new_file_path);
+            if !status.changes.is_empty() {
+                return Err(ModderError::DirtySrc(format!(
+                    "src has {} unsaved change(s), not restoring. Save them first or use --force to discard them.",
+                    status.changes.len()
+                )));
             }
         }
 
-        Ok(())
+        let files = self.snapshot_files(name, version)?;
+
+        self.commit_checkout(name, version, &files)?;
+
+        Ok(version)
     }
 
-    fn explore_folders_recursive(
-        start: &Path,
-        fileType: &Option<String>,
-        list: &mut Vec<PathBuf>,
-    ) -> Result<(), std::io::Error> {
-        // copy the files in the src folder to the branch folder
-        let srcFiles = std::fs::read_dir(&start)?;
+    pub fn restore_files(
+        &self,
+        name: &str,
+        version: Option<i32>,
+        patterns: &[String],
+    ) -> Result<(i32, Vec<PathBuf>), ModderError> {
+        let version = self.resolve_version(name, version)?;
+        let files = self.matching_snapshot_files(name, version, patterns)?;
 
-        for file in srcFiles {
-            let file = file?;
-            let file_path = file.path();
+        Workspace::copy_files(&self.src_folder_path(), &files)?;
 
-            if file_path.is_dir() {
-                Workspace::explore_folders_recursive(&file_path, fileType, list)?;
-            } else {
-                if Workspace::allow_copy(&file_path, fileType) {
-                    list.push(file_path);
-                }
-            }
-        }
+        let restored = files.into_iter().map(|(_, relative)| relative).collect();
 
-        Ok(())
+        Ok((version, restored))
     }
 
-    fn recurcive_copy(
-        src: &Path,
-        dest: &Path,
-        fileType: &Option<String>,
-    ) -> Result<(), std::io::Error> {
-        // copy the files in the src folder to the branch folder
-        let mut files = vec![];
+    /// Copies the matching files of any branch version into src, returning the
+    /// files that were added and the ones that replaced an existing file
+    pub fn pick_files(
+        &self,
+        name: &str,
+        version: i32,
+        patterns: &[String],
+    ) -> Result<(Vec<PathBuf>, Vec<PathBuf>), ModderError> {
+        let src_folder = self.src_folder_path();
+        let files = self.matching_snapshot_files(name, version, patterns)?;
 
-        // we get the entire list of files we want to copy first
-        Workspace::explore_folders_recursive(src, fileType, &mut files)?;
+        let (overwritten, added): (Vec<PathBuf>, Vec<PathBuf>) = files
+            .iter()
+            .map(|(_, relative)| relative.clone())
+            .partition(|relative| src_folder.join(relative).exists());
 
-        println!("Started copying files to branch folder.");
+        Workspace::copy_files(&src_folder, &files)?;
 
-        // create a set of parent folders
-        let mut hashset_folders = std::collections::HashSet::new();
+        Ok((added, overwritten))
+    }
 
-        // using a set we can ensure we only create the folders once
-        // avoiding us future checks against the operating system
-        for file in &files {
-            let parent = file.parent();
-            let folder = match parent {
-                Some(f) => f,
-                None => continue,
-            };
+    fn matching_snapshot_files(
+        &self,
+        name: &str,
+        version: i32,
+        patterns: &[String],
+    ) -> Result<Vec<(PathBuf, PathBuf)>, ModderError> {
+        let compiled = Workspace::compile_patterns(patterns)?;
 
-            hashset_folders.insert(folder);
-        }
+        let mut files = self.snapshot_files(name, version)?;
 
+        // only keep the files matching one of the patterns
+        files.retain(|(_, relative)| Workspace::matches_any(&compiled, relative));
 
-        let mut final_hashset_folders = std::collections::HashSet::new();
+        if files.is_empty() {
+            return Err(ModderError::Invalid(
+                format!("No files in version {} of branch {} match the given paths.", version, name),
+            ));
+        }
 
-        // when src/test/files/ and src/test/files/deeper/ are both in the list
-        // we remove the smallest folder from the list
-        // so we avoid possible systemcalls to check if the folder exists
-        for folder in &hashset_folders {
-            let mut remove = false;
-            
-            for folder2 in &hashset_folders {
-                if folder.starts_with(folder2) && !folder.eq(folder2) {
-                    remove = true;
-                    break;
-                }
+        Ok(files)
+    }
+
+    /// Checks that the version exists in the branch, defaulting to the latest one
+    fn resolve_version(&self, name: &str, version: Option<i32>) -> Result<i32, ModderError> {
+        // check if branch exists
+        let branch = match self.find_branch(name) {
+            Some(b) => b,
+            None => {
+                return Err(ModderError::BranchNotFound(name.to_string()))
             }
+        };
 
-            if !remove {
-                final_hashset_folders.insert(folder);
+        let latest = match branch.latest_version() {
+            Some(v) => v,
+            None => {
+                return Err(ModderError::NoVersions(name.to_string()))
             }
+        };
+
+        // default to the latest version of the branch
+        let version = version.unwrap_or(latest);
+
+        if version < 1 || version > latest {
+            return Err(ModderError::VersionNotFound {
+                branch: name.to_string(),
+                version,
+                latest,
+            });
         }
 
+        if !self.version_exists(name, version) {
+            return Err(ModderError::VersionPruned {
+                branch: name.to_string(),
+                version,
+            });
+        }
 
-        for folder in final_hashset_folders {
-            let new_folder = dest.join(folder.strip_prefix(src).unwrap());
-            _ = std::fs::create_dir_all(new_folder);
+        Ok(version)
+    }
+
+    fn version_exists(&self, name: &str, version: i32) -> bool {
+        self.manifest_path(name, version).exists() || self.version_folder_path(name, version).exists()
+    }
+
+    /// Lists the files of a saved version as pairs of where the content is stored
+    /// and the path relative to src it belongs to
+    fn snapshot_files(&self, name: &str, version: i32) -> Result<Vec<(PathBuf, PathBuf)>, ModderError> {
+        let manifest_file = self.manifest_path(name, version);
+
+        if manifest_file.exists() {
+            let manifest = Manifest::load(&manifest_file)?;
+            let store = self.object_store();
+
+            let files = manifest
+                .files
+                .iter()
+                .map(|(relative, entry)| (store.object_path(&entry.hash), PathBuf::from(relative)))
+                .collect();
+
+            return Ok(files);
         }
 
-        for file in files {
-            let new_file = dest.join(file.strip_prefix(src).unwrap());
-            _ = std::fs::copy(&file, new_file);
+        // versions saved before the object store are plain copies of src
+        let version_folder = self.version_folder_path(name, version);
+
+        if !version_folder.exists() {
+            return Err(ModderError::VersionPruned {
+                branch: name.to_string(),
+                version,
+            });
         }
 
-        println!("Copied files to branch folder.");
+        let mut files = vec![];
+        Workspace::explore_folders_recursive(&version_folder, &None, &self.ignore_files_pattern, &mut files)?;
 
-        Ok(())
+        let files = files
+            .into_iter()
+            .map(|f| {
+                let relative = f.strip_prefix(&version_folder).unwrap().to_path_buf();
+                (f, relative)
+            })
+            .collect();
+
+        Ok(files)
+    }
+
+    /// Lists every saved version of the branch, newest first
+    pub fn history(&self, name: &str) -> Result<Vec<VersionLog>, ModderError> {
+        let latest = self.resolve_version(name, None)?;
+
+        let tags = self.load_tags()?;
+
+        let mut history = vec![];
+        let mut previous = BTreeMap::new();
+
+        for version in 1..=latest {
+            // pruned versions leave a gap in the numbers
+            if !self.version_exists(name, version) {
+                continue;
+            }
+
+            let files = self.version_hashes(name, version)?;
+
+            let manifest_file = self.manifest_path(name, version);
+            let manifest = if manifest_file.exists() {
+                Some(Manifest::load(&manifest_file)?)
+            } else {
+                None
+            };
+
+            let files_changed = FileChanges::between(&previous, &files).len();
+
+            history.push(VersionLog {
+                version,
+                created: manifest.as_ref().map(|m| m.created),
+                message: manifest.as_ref().and_then(|m| m.message.clone()),
+                tags: tags
+                    .iter()
+                    .filter(|t| t.branch == name && t.version == version)
+                    .map(|t| t.name.clone())
+                    .collect(),
+                files_changed,
+            });
+
+            previous = files;
+        }
+
+        history.reverse();
+
+        Ok(history)
+    }
+
+    /// Compares a saved version against another one, or against src when `to` is missing
+    pub fn diff(&self, from: (&str, i32), to: Option<(&str, i32)>) -> Result<FileChanges, ModderError> {
+        let old = self.version_hashes(from.0, from.1)?;
+
+        let new = match to {
+            Some((branch, version)) => self.version_hashes(branch, version)?,
+            None => self.src_hashes()?,
+        };
+
+        Ok(FileChanges::between(&old, &new))
+    }
+
+    pub fn load_stashes(&self) -> Result<Vec<Stash>, ModderError> {
+        let stash_file = self.stashes_path();
+
+        if !stash_file.exists() {
+            return Ok(vec![]);
+        }
+
+        metadata::read(&stash_file)
+    }
+
+    fn save_stashes(&self, stashes: &[Stash]) -> Result<(), ModderError> {
+        Ok(metadata::write(&self.stashes_path(), stashes)?)
+    }
+
+    /// Moves the unsaved changes of src into a new stash and brings src back to the latest version
+    pub fn stash_push(&mut self, message: Option<String>) -> Result<usize, ModderError> {
+        let status = self.status()?;
+
+        if status.changes.is_empty() {
+            return Err(ModderError::Invalid("No unsaved changes to stash.".to_string()));
+        }
+
+        let src_folder = self.src_folder_path();
+        let store = self.object_store();
+        let changes = &status.changes;
+
+        let mut stash = Stash::new(status.branch.clone(), status.version, message);
+
+        for path in changes.added.iter().chain(changes.modified.iter()) {
+            let file = src_folder.join(path);
+            let hash = store.store(&file)?;
+
+            stash.files.insert(path.clone(), ManifestEntry::new(hash, &std::fs::metadata(&file)?)?);
+        }
+
+        stash.removed = changes.removed.clone();
+
+        // the stash is recorded before src is touched, if reverting src fails the changes stay in both
+        let mut stashes = self.load_stashes()?;
+        stashes.insert(0, stash);
+        self.save_stashes(&stashes)?;
+
+        // bring the stashed files back to how they were saved, leaving everything else alone
+        for path in &changes.added {
+            std::fs::remove_file(src_folder.join(path))?;
+        }
+
+        if let Some(base) = &status.base {
+            let mut files = self.snapshot_files(&base.branch, base.version)?;
+
+            files.retain(|(_, relative)| {
+                let relative = Workspace::relative_path_string(relative);
+                changes.modified.contains(&relative) || changes.removed.contains(&relative)
+            });
+
+            Workspace::copy_files(&src_folder, &files)?;
+        }
+
+        // src is back at the saved version, a merge in it went into the stash
+        if let Some(branch) = self.branches.iter_mut().find(|b| b.name == status.branch) {
+            branch.pending_merge = None;
+        }
+
+        Ok(changes.len())
+    }
+
+    /// Applies a stash on top of src and removes it from the stash list
+    pub fn stash_pop(&mut self, index: usize) -> Result<Stash, ModderError> {
+        let mut stashes = self.load_stashes()?;
+
+        if index >= stashes.len() {
+            return Err(ModderError::StashNotFound(index));
+        }
+
+        let stash = &stashes[index];
+
+        // refuse to overwrite files that were changed since the stash was made
+        if self.info.current_branch.is_some() {
+            let changes = self.status()?.changes;
+
+            if let Some(path) = stash.paths().find(|p| changes.contains(p)) {
+                return Err(ModderError::DirtySrc(
+                    format!("Unsaved changes to {} would be overwritten, save or stash them first.", path),
+                ));
+            }
+        }
+
+        let src_folder = self.src_folder_path();
+        let store = self.object_store();
+
+        let files: Vec<(PathBuf, PathBuf)> = stash
+            .files
+            .iter()
+            .map(|(relative, entry)| (store.object_path(&entry.hash), PathBuf::from(relative)))
+            .collect();
+
+        Workspace::copy_files(&src_folder, &files)?;
+
+        for path in &stash.removed {
+            let file = src_folder.join(path);
+
+            if file.exists() {
+                std::fs::remove_file(file)?;
+            }
+        }
+
+        let stash = stashes.remove(index);
+        self.save_stashes(&stashes)?;
+
+        Ok(stash)
+    }
+
+    pub fn stash_drop(&mut self, index: usize) -> Result<Stash, ModderError> {
+        let mut stashes = self.load_stashes()?;
+
+        if index >= stashes.len() {
+            return Err(ModderError::StashNotFound(index));
+        }
+
+        let stash = stashes.remove(index);
+        self.save_stashes(&stashes)?;
+
+        Ok(stash)
+    }
+
+    pub fn load_tags(&self) -> Result<Vec<Tag>, ModderError> {
+        let tag_file = self.tags_path();
+
+        if !tag_file.exists() {
+            return Ok(vec![]);
+        }
+
+        metadata::read(&tag_file)
+    }
+
+    fn save_tags(&self, tags: &[Tag]) -> Result<(), ModderError> {
+        Ok(metadata::write(&self.tags_path(), tags)?)
+    }
+
+    /// Branch names become folder names and are used in branch@version
+    /// A branch taking the name of a tag would hide the tag from every revision
+    fn validate_branch_name(&self, name: &str) -> Result<(), ModderError> {
+        if !branch::is_valid_name(name) {
+            return Err(ModderError::InvalidName {
+                kind: "branch",
+                name: name.to_string(),
+            });
+        }
+
+        if self.find_tag(name)?.is_some() {
+            return Err(ModderError::TagExists(name.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Tags share the revision syntax with branches, so they can't reuse a branch name
+    fn validate_tag_name(&self, name: &str, tags: &[Tag]) -> Result<(), ModderError> {
+        if !branch::is_valid_name(name) {
+            return Err(ModderError::InvalidName {
+                kind: "tag",
+                name: name.to_string(),
+            });
+        }
+
+        if self.find_branch(name).is_some() {
+            return Err(ModderError::BranchExists(name.to_string()));
+        }
+
+        if tags.iter().any(|t| t.name == name) {
+            return Err(ModderError::TagExists(name.to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub fn add_tag(&mut self, name: &str, branch: &str, version: i32) -> Result<(), ModderError> {
+        let mut tags = self.load_tags()?;
+
+        self.validate_tag_name(name, &tags)?;
+        self.resolve_version(branch, Some(version))?;
+
+        tags.push(Tag::new(name.to_string(), branch.to_string(), version));
+        self.save_tags(&tags)
+    }
+
+    pub fn remove_tag(&mut self, name: &str) -> Result<Tag, ModderError> {
+        let mut tags = self.load_tags()?;
+
+        let index = match tags.iter().position(|t| t.name == name) {
+            Some(i) => i,
+            None => {
+                return Err(ModderError::TagNotFound(name.to_string()))
+            }
+        };
+
+        let tag = tags.remove(index);
+        self.save_tags(&tags)?;
+
+        Ok(tag)
+    }
+
+    pub fn find_tag(&self, name: &str) -> Result<Option<Tag>, ModderError> {
+        Ok(self.load_tags()?.into_iter().find(|t| t.name == name))
+    }
+
+    /// Compares src against the latest saved version of the current branch
+    pub fn status(&self) -> Result<Status, ModderError> {
+        let branch = match &self.info.current_branch {
+            Some(b) => b.clone(),
+            None => {
+                return Err(ModderError::NoBranchSelected)
+            }
+        };
+
+        let version = match self.find_branch(&branch) {
+            Some(b) => b.latest_version(),
+            None => {
+                return Err(ModderError::BranchNotFound(branch.clone()))
+            }
+        };
+
+        let base = self.saved_base(&branch);
+
+        let saved = match &base {
+            Some(base) => self.version_hashes(&base.branch, base.version)?,
+            None => BTreeMap::new(),
+        };
+
+        let changes = FileChanges::between(&saved, &self.src_hashes()?);
+
+        Ok(Status {
+            branch,
+            version,
+            base,
+            changes,
+        })
+    }
+
+    /// The saved version src started from on a branch, its latest version or else the version
+    /// it was created from, since src still holds that one until the branch is saved
+    fn saved_base(&self, name: &str) -> Option<BranchParent> {
+        let branch = self.find_branch(name)?;
+
+        if let Some(version) = branch.latest_version() {
+            return Some(BranchParent {
+                branch: branch.name.clone(),
+                version,
+            });
+        }
+
+        let parent = branch.parent.as_ref()?;
+
+        // a parent without versions, or one that was deleted since, leaves nothing to compare
+        if parent.version > 0 && self.find_branch(&parent.branch).is_some() {
+            Some(parent.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Finds the branch and version a revision points to, filling in the current branch and latest version
+    pub fn resolve_revision(&self, revision: &Revision) -> Result<(String, i32), ModderError> {
+        match self.resolve_target(revision)? {
+            (branch, Some(version)) => Ok((branch, version)),
+            (branch, None) => {
+                let version = self.resolve_version(&branch, None)?;
+                Ok((branch, version))
+            }
+        }
+    }
+
+    /// Like `resolve_revision`, but a branch named without a version resolves to no version
+    /// instead of its latest one, so branches that were never saved can still be targeted
+    pub fn resolve_target(&self, revision: &Revision) -> Result<(String, Option<i32>), ModderError> {
+        let name = match revision.branch.as_ref().or(self.info.current_branch.as_ref()) {
+            Some(b) => b.clone(),
+            None => {
+                return Err(ModderError::NoBranchSelected)
+            }
+        };
+
+        let branch = match self.find_branch(&name) {
+            Some(b) => b,
+            None => {
+                // a name that isn't a branch can be a tag
+                let tag = match self.find_tag(&name)? {
+                    Some(t) => t,
+                    None => {
+                        let known = self.branches.iter().map(|b| b.name.clone()).collect::<Vec<_>>();
+
+                        return Err(ModderError::UnknownRevision {
+                            name: name.clone(),
+                            branches: known,
+                        });
+                    }
+                };
+
+                let version = match revision.version {
+                    VersionSpec::Latest => tag.version,
+                    VersionSpec::Back(n) => tag.version - n,
+                    VersionSpec::Number(_) => {
+                        return Err(ModderError::Invalid(
+                            format!("'{}' is a tag, it can't be given a version number.", name),
+                        ))
+                    }
+                };
+
+                if version < 1 {
+                    return Err(ModderError::Invalid(
+                        format!("{} goes back further than the first version of {}.", name, tag.branch),
+                    ));
+                }
+
+                let version = self.resolve_version(&tag.branch, Some(version))?;
+                return Ok((tag.branch, Some(version)));
+            }
+        };
+
+        let version = match revision.version {
+            VersionSpec::Latest => None,
+            VersionSpec::Number(n) => Some(n),
+            VersionSpec::Back(n) => {
+                let latest = match branch.latest_version() {
+                    Some(v) => v,
+                    None => {
+                        return Err(ModderError::NoVersions(name.to_string()))
+                    }
+                };
+
+                if latest - n < 1 {
+                    return Err(ModderError::Invalid(format!(
+                        "{}@~{} goes back further than the first version of {}, the latest is {}.",
+                        name, n, name, latest
+                    )));
+                }
+
+                Some(latest - n)
+            }
+        };
+
+        match version {
+            Some(v) => Ok((name.clone(), Some(self.resolve_version(&name, Some(v))?))),
+            None => Ok((name, None)),
+        }
+    }
+
+    /// Maps every file of src that would be saved, relative to src, to its content hash
+    pub fn src_hashes(&self) -> Result<BTreeMap<String, String>, ModderError> {
+        let src_folder = self.src_folder_path();
+        let files = Workspace::list_src_files(&src_folder, &self.info, &self.ignore_files_pattern)?;
+
+        let mut hashes = BTreeMap::new();
+
+        for file in files {
+            let relative = Workspace::relative_path_string(file.strip_prefix(&src_folder).unwrap());
+            hashes.insert(relative, ObjectStore::hash_file(&file)?);
+        }
+
+        Ok(hashes)
+    }
+
+    /// Maps every file of a saved version, relative to src, to its content hash
+    fn version_hashes(&self, name: &str, version: i32) -> Result<BTreeMap<String, String>, ModderError> {
+        let manifest_file = self.manifest_path(name, version);
+
+        if manifest_file.exists() {
+            let manifest = Manifest::load(&manifest_file)?;
+
+            return Ok(manifest.files.into_iter().map(|(path, entry)| (path, entry.hash)).collect());
+        }
+
+        // versions saved before the manifests have to be hashed from disk
+        let mut hashes = BTreeMap::new();
+
+        for (stored, relative) in self.snapshot_files(name, version)? {
+            hashes.insert(Workspace::relative_path_string(&relative), ObjectStore::hash_file(&stored)?);
+        }
+
+        Ok(hashes)
+    }
+
+    /// Relative paths are always matched with forward slashes so patterns
+    /// behave the same on every platform
+    fn relative_path_string(path: &Path) -> String {
+        path.to_string_lossy().replace('\\', "/")
+    }
+
+    fn compile_patterns(patterns: &[String]) -> Result<Vec<glob::Pattern>, ModderError> {
+        let mut compiled = vec![];
+
+        for pattern in patterns {
+            let pattern = glob::Pattern::new(pattern.trim_end_matches('/')).map_err(|e| {
+                ModderError::Invalid(format!("Invalid file pattern {}: {}", pattern, e))
+            })?;
+
+            compiled.push(pattern);
+        }
+
+        Ok(compiled)
+    }
+
+    /// A pattern matching a folder matches everything inside of it
+    fn matches_any(patterns: &[glob::Pattern], relative: &Path) -> bool {
+        relative.ancestors().any(|path| {
+            let path = Workspace::relative_path_string(path);
+            patterns.iter().any(|p| p.matches_with(&path, Workspace::match_options()))
+        })
+    }
+
+    fn match_options() -> glob::MatchOptions {
+        glob::MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        }
+    }
+
+    pub fn find_branch(&self, name: &str) -> Option<&Branch> {
+        self.branches.iter().find(|b| b.name == name)
+    }
+
+    pub fn version_folder_path(&self, branch: &str, version: i32) -> PathBuf {
+        self.branches_folder_path()
+            .join(branch)
+            .join(format!("{}", version))
+    }
+
+    pub fn manifest_path(&self, branch: &str, version: i32) -> PathBuf {
+        self.branches_folder_path()
+            .join(branch)
+            .join(format!("{}.manifest", version))
+    }
+
+    pub fn objects_folder_path(&self) -> PathBuf {
+        self.root_folder.join("branches/.objects")
+    }
+
+    pub fn object_store(&self) -> ObjectStore {
+        ObjectStore::new(self.objects_folder_path())
+    }
+
+    /// The tags pointing at a branch and the indexes of the stashes made on it,
+    /// a deleted branch leaves them dangling until it is undeleted
+    pub fn branch_references(&self, name: &str) -> Result<(Vec<Tag>, Vec<usize>), ModderError> {
+        let tags = self.load_tags()?.into_iter().filter(|t| t.branch == name).collect();
+
+        let stashes = self
+            .load_stashes()?
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.branch == name)
+            .map(|(index, _)| index)
+            .collect();
+
+        Ok((tags, stashes))
+    }
+
+    /// Moves a branch and its versions to the trash, the current branch is only removed when forced
+    pub fn remove_branch_by_name(&mut self, name: &str, force: bool) -> Result<(), ModderError> {
+        // check if branch exists
+        let found = self.branches.iter().find(|b| b.name == name);
+
+        let branch = match found {
+            Some(b) => b.clone(),
+            None => {
+                return Err(ModderError::BranchNotFound(name.to_string()))
+            }
+        };
+
+        let is_current = self.info.current_branch.as_deref() == Some(name);
+
+        if is_current && !force {
+            return Err(ModderError::CurrentBranch(name.to_string()));
+        }
+
+        let deleted = Utc::now();
+        let id = format!("{}-{}", branch.name, deleted.timestamp_millis());
+
+        // move the branch folder to the trash
+        let branch_folder = self.branches_folder_path().join(&branch.name);
+        std::fs::create_dir_all(self.trash_folder_path())?;
+
+        if branch_folder.exists() {
+            std::fs::rename(&branch_folder, self.trash_folder_path().join(&id))?;
+        }
+
+        let mut trash = self.load_trash()?;
+        trash.push(TrashedBranch { id, branch, deleted });
+        self.save_trash(&trash)?;
+
+        if is_current {
+            self.info.current_branch = None;
+        }
+
+        // remove the branch from the branches list
+        self.branches.retain(|b| b.name != name);
+
+        Ok(())
+    }
+
+    /// Brings back the most recently deleted branch with that name
+    pub fn undelete_branch(&mut self, name: &str) -> Result<(), ModderError> {
+        let mut trash = self.load_trash()?;
+
+        let index = match trash.iter().rposition(|t| t.branch.name == name) {
+            Some(i) => i,
+            None => {
+                return Err(ModderError::TrashedBranchNotFound(name.to_string()))
+            }
+        };
+
+        if self.find_branch(name).is_some() {
+            return Err(ModderError::BranchExists(name.to_string()));
+        }
+
+        // checked before the folder moves back, add_branch would only notice afterwards
+        self.validate_branch_name(name)?;
+
+        let trashed = trash.remove(index);
+        let trashed_folder = self.trash_folder_path().join(&trashed.id);
+
+        if trashed_folder.exists() {
+            std::fs::rename(&trashed_folder, self.branches_folder_path().join(name))?;
+        }
+
+        self.save_trash(&trash)?;
+        self.add_branch(trashed.branch)?;
+
+        Ok(())
+    }
+
+    /// Permanently removes every branch in the trash, returning how many were removed
+    pub fn empty_trash(&mut self) -> Result<usize, ModderError> {
+        let trash = self.load_trash()?;
+
+        for trashed in &trash {
+            let trashed_folder = self.trash_folder_path().join(&trashed.id);
+
+            if trashed_folder.exists() {
+                std::fs::remove_dir_all(trashed_folder)?;
+            }
+        }
+
+        self.save_trash(&[])?;
+
+        Ok(trash.len())
+    }
+
+    pub fn load_trash(&self) -> Result<Vec<TrashedBranch>, ModderError> {
+        let trash_file = self.trash_path();
+
+        if !trash_file.exists() {
+            return Ok(vec![]);
+        }
+
+        metadata::read(&trash_file)
+    }
+
+    fn save_trash(&self, trash: &[TrashedBranch]) -> Result<(), ModderError> {
+        std::fs::create_dir_all(self.trash_folder_path())?;
+
+        Ok(metadata::write(&self.trash_path(), trash)?)
+    }
+
+    pub fn save_info(&self) -> Result<(), ModderError> {
+        Ok(metadata::write(&self.info_path(), &self.info)?)
+    }
+
+    pub fn save_branches(&self) -> Result<(), ModderError> {
+        Ok(metadata::write(&self.branches_path(), &self.branches)?)
+    }
+
+    pub fn save(&self) -> Result<(), ModderError> {
+        self.save_info()?;
+        self.save_branches()?;
+
+        Ok(())
+    }
+
+    pub fn save_current_state(&mut self, message: Option<String>, tags: Vec<String>) -> Result<i32, ModderError> {
+        // Save the current state of the mod to the current branch
+        let src_folder = self.src_folder_path();
+        let store = self.object_store();
+
+        let current_branch = match &self.info.current_branch {
+            Some(b) => b.clone(),
+            None => {
+                return Err(ModderError::NoBranchSelected)
+            }
+        };
+
+        let version = match self.find_branch(&current_branch) {
+            Some(b) => b.version,
+            None => {
+                return Err(ModderError::BranchNotFound(current_branch.clone()))
+            }
+        };
+
+        // check the tags before anything is stored so a taken name doesn't leave half a save
+        let saved_tags = self.load_tags()?;
+
+        for tag in &tags {
+            self.validate_tag_name(tag, &saved_tags)?;
+        }
+
+        let files = Workspace::list_src_files(&src_folder, &self.info, &self.ignore_files_pattern)?;
+
+        eprintln!("Started saving files to the object store.");
+
+        // unchanged files hash to an object that already exists and are not copied again
+        let mut manifest = Manifest::new(version, message);
+
+        let mut failures = vec![];
+
+        // store whatever can be stored so every file that can't is reported at once
+        for file in files {
+            let relative = Workspace::relative_path_string(file.strip_prefix(&src_folder).unwrap());
+
+            let stored = store.store(&file).and_then(|hash| {
+                let metadata = std::fs::metadata(&file)?;
+                manifest.add_file(relative, hash, &metadata)
+            });
+
+            if let Err(e) = stored {
+                failures.push(FileFailure::new(file, e));
+            }
+        }
+
+        ModderError::check_files("store", failures)?;
+
+        // the version only counts once its manifest and every stored file are in place
+        self.commit_save(&current_branch, &manifest, tags)?;
+
+        eprintln!("Saved files to the object store.");
+
+        Ok(version)
+    }
+
+    fn allow_copy(file: &Path, allowed_file_type: &Option<String>) -> bool {
+        let allowed_file_type = match allowed_file_type {
+            Some(t) => t,
+            None => return true,
+        };
+        let file_name = file.file_name().unwrap().to_str().unwrap();
+
+        file_name.ends_with(allowed_file_type)
+    }
+
+    /// Lists the files of src that are saved, following the workspace settings and ignore patterns
+    fn list_src_files(src: &Path, info: &ModInfo, ignore: &IgnorePatterns) -> Result<Vec<PathBuf>, ModderError> {
+        let mut files = vec![];
+
+        if info.top_files_only {
+            Workspace::explore_top_level(src, &info.file_type, ignore, &mut files)?;
+        } else {
+            Workspace::explore_folders_recursive(src, &info.file_type, ignore, &mut files)?;
+        }
+
+        Ok(files)
+    }
+
+    fn explore_top_level(
+        src: &Path,
+        fileType: &Option<String>,
+        ignore: &IgnorePatterns,
+        list: &mut Vec<PathBuf>,
+    ) -> Result<(), ModderError> {
+        // only the files directly in the src folder are listed
+        let srcFiles = std::fs::read_dir(src)?;
+
+        for file in srcFiles {
+            let file = file?;
+            let file_name = file.file_name();
+            let file_path = file.path();
+
+            if file_path.is_file()
+                && Workspace::allow_copy(&file_path, fileType)
+                && !ignore.is_ignored(Path::new(&file_name), false)
+            {
+                list.push(file_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn explore_folders_recursive(
+        start: &Path,
+        fileType: &Option<String>,
+        ignore: &IgnorePatterns,
+        list: &mut Vec<PathBuf>,
+    ) -> Result<(), ModderError> {
+        Workspace::explore_folder(start, start, fileType, ignore, list)
+    }
+
+    fn explore_folder(
+        root: &Path,
+        folder: &Path,
+        fileType: &Option<String>,
+        ignore: &IgnorePatterns,
+        list: &mut Vec<PathBuf>,
+    ) -> Result<(), ModderError> {
+        // copy the files in the src folder to the branch folder
+        let srcFiles = std::fs::read_dir(folder)?;
+
+        for file in srcFiles {
+            let file = file?;
+            let file_path = file.path();
+            let is_dir = file_path.is_dir();
+
+            // ignored folders are skipped entirely, like git does
+            if ignore.is_ignored(file_path.strip_prefix(root).unwrap(), is_dir) {
+                continue;
+            }
+
+            if is_dir {
+                Workspace::explore_folder(root, &file_path, fileType, ignore, list)?;
+            } else if Workspace::allow_copy(&file_path, fileType) {
+                list.push(file_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies every (stored file, relative path) pair to the same relative location in `dest`
+    fn copy_files(dest: &Path, files: &[(PathBuf, PathBuf)]) -> Result<(), ModderError> {
+        eprintln!("Started copying files to src folder.");
+
+        // create a set of parent folders
+        let mut hashset_folders = std::collections::HashSet::new();
+
+        // using a set we can ensure we only create the folders once
+        // avoiding us future checks against the operating system
+        for (_, relative) in files {
+            let parent = relative.parent();
+            let folder = match parent {
+                Some(f) => f,
+                None => continue,
+            };
+
+            hashset_folders.insert(folder);
+        }
+
+
+        let mut final_hashset_folders = std::collections::HashSet::new();
+
+        // when src/test/files/ and src/test/files/deeper/ are both in the list
+        // we remove the smallest folder from the list
+        // so we avoid possible systemcalls to check if the folder exists
+        for folder in &hashset_folders {
+            let mut remove = false;
+            
+            for folder2 in &hashset_folders {
+                if folder2.starts_with(folder) && !folder.eq(folder2) {
+                    remove = true;
+                    break;
+                }
+            }
+
+            if !remove {
+                final_hashset_folders.insert(folder);
+            }
+        }
+
+
+        let mut failures = vec![];
+
+        for folder in final_hashset_folders {
+            if let Err(e) = std::fs::create_dir_all(dest.join(folder)) {
+                failures.push(FileFailure::new(dest.join(folder), e));
+            }
+        }
+
+        // keep going after a failure so every file that didn't make it is reported
+        for (stored, relative) in files {
+            if let Err(e) = Workspace::copy_file(stored, &dest.join(relative)) {
+                failures.push(FileFailure::new(dest.join(relative), e));
+            }
+        }
+
+        ModderError::check_files("copy", failures)?;
+
+        eprintln!("Copied files to src folder.");
+
+        Ok(())
+    }
+
+    /// Copies a single file into an existing folder and checks the copy is complete
+    fn copy_file(stored: &Path, dest: &Path) -> Result<(), std::io::Error> {
+        let copied = std::fs::copy(stored, dest)?;
+
+        if copied != std::fs::metadata(stored)?.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "copy is incomplete",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::ops::{Deref, DerefMut};
+
+    use super::{MergeResult, SwitchResult, Workspace};
+    use crate::{
+        args::revision::Revision,
+        branch::Branch,
+        error::ModderError,
+        mod_info::ModInfo,
+    };
+
+    /// A fresh workspace in the temp folder, removed again when dropped
+    pub(crate) struct TestWorkspace(pub Workspace);
+
+    impl TestWorkspace {
+        pub(crate) fn new(name: &str) -> TestWorkspace {
+            let root = std::env::temp_dir().join(format!("moddercli-test-{}-{}", std::process::id(), name));
+            _ = std::fs::remove_dir_all(&root);
+
+            let info = ModInfo::new("test".to_string(), "tester".to_string(), String::new(), Some("main".to_string()));
+
+            TestWorkspace(Workspace::init(root, info).unwrap())
+        }
+
+        pub(crate) fn write(&self, relative: &str, content: &str) {
+            let file = self.src_folder_path().join(relative);
+
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, content).unwrap();
+        }
+
+        pub(crate) fn read(&self, relative: &str) -> Option<String> {
+            std::fs::read_to_string(self.src_folder_path().join(relative)).ok()
+        }
+
+        pub(crate) fn remove(&self, relative: &str) {
+            std::fs::remove_file(self.src_folder_path().join(relative)).unwrap();
+        }
+
+        pub(crate) fn save(&mut self) -> i32 {
+            self.0.save_current_state(None, vec![]).unwrap()
+        }
+
+        /// Creates a branch from the current one like `branch create`
+        pub(crate) fn create_branch(&mut self, name: &str) {
+            let mut branch = Branch::new(name.to_string(), String::new(), 1);
+            branch.parent = self.fork_point();
+
+            self.add_branch(branch).unwrap();
+        }
+
+        pub(crate) fn switch(&mut self, name: &str) {
+            self.0.switch_branch(name, None, false).unwrap();
+        }
+    }
+
+    impl Deref for TestWorkspace {
+        type Target = Workspace;
+
+        fn deref(&self) -> &Workspace {
+            &self.0
+        }
+    }
+
+    impl DerefMut for TestWorkspace {
+        fn deref_mut(&mut self) -> &mut Workspace {
+            &mut self.0
+        }
+    }
+
+    impl Drop for TestWorkspace {
+        fn drop(&mut self) {
+            _ = std::fs::remove_dir_all(&self.root_folder);
+        }
+    }
+
+    fn resolve(workspace: &Workspace, revision: &str) -> Result<(String, i32), ModderError> {
+        workspace.resolve_revision(&revision.parse::<Revision>().unwrap())
+    }
+
+    /// main with versions 1 to 3 and the tag release on version 2
+    fn tagged_workspace(name: &str) -> TestWorkspace {
+        let mut workspace = TestWorkspace::new(name);
+
+        for content in ["one", "two", "three"] {
+            workspace.write("a.txt", content);
+            workspace.save();
+        }
+
+        workspace.add_tag("release", "main", 2).unwrap();
+
+        workspace
+    }
+
+    #[test]
+    fn resolves_versions_of_the_current_branch() {
+        let workspace = tagged_workspace("resolve-current");
+
+        assert_eq!(resolve(&workspace, "@latest").unwrap(), ("main".to_string(), 3));
+        assert_eq!(resolve(&workspace, "@prev").unwrap(), ("main".to_string(), 2));
+        assert_eq!(resolve(&workspace, "@~").unwrap(), ("main".to_string(), 2));
+        assert_eq!(resolve(&workspace, "@~2").unwrap(), ("main".to_string(), 1));
+        assert_eq!(resolve(&workspace, "main@1").unwrap(), ("main".to_string(), 1));
+    }
+
+    #[test]
+    fn resolves_tags_and_counts_back_from_them() {
+        let workspace = tagged_workspace("resolve-tags");
+
+        assert_eq!(resolve(&workspace, "release").unwrap(), ("main".to_string(), 2));
+        assert_eq!(resolve(&workspace, "release@~1").unwrap(), ("main".to_string(), 1));
+        assert!(matches!(resolve(&workspace, "release@~2"), Err(ModderError::Invalid(_))));
+        assert!(matches!(resolve(&workspace, "release@1"), Err(ModderError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_revisions_outside_the_history() {
+        let workspace = tagged_workspace("resolve-errors");
+
+        assert!(matches!(resolve(&workspace, "@~3"), Err(ModderError::Invalid(_))));
+        assert!(matches!(resolve(&workspace, "main@9"), Err(ModderError::VersionNotFound { .. })));
+        assert!(matches!(resolve(&workspace, "nope"), Err(ModderError::UnknownRevision { .. })));
+    }
+
+    /// main saved with a.txt, b.txt and c.txt and the branch tex created from it
+    fn forked_workspace(name: &str) -> TestWorkspace {
+        let mut workspace = TestWorkspace::new(name);
+
+        for file in ["a.txt", "b.txt", "c.txt"] {
+            workspace.write(file, "base");
+        }
+
+        workspace.save();
+        workspace.create_branch("tex");
+
+        workspace
+    }
+
+    fn merge(workspace: &mut Workspace, source: &str, ours: &[&str], theirs: &[&str]) -> MergeResult {
+        let ours: Vec<String> = ours.iter().map(|p| p.to_string()).collect();
+        let theirs: Vec<String> = theirs.iter().map(|p| p.to_string()).collect();
+
+        workspace.merge_branch(source, &ours, &theirs).unwrap()
+    }
+
+    #[test]
+    fn merge_takes_changes_made_on_one_side() {
+        let mut workspace = forked_workspace("merge-one-sided");
+
+        workspace.switch("tex");
+        workspace.write("a.txt", "tex");
+        workspace.remove("c.txt");
+        workspace.write("d.txt", "new");
+        workspace.save();
+
+        workspace.switch("main");
+        workspace.write("b.txt", "main");
+        workspace.save();
+
+        match merge(&mut workspace, "tex", &[], &[]) {
+            MergeResult::Merged { base, updated, removed } => {
+                assert_eq!(base, Some(("main".to_string(), 1)));
+                assert_eq!(updated, vec!["a.txt", "d.txt"]);
+                assert_eq!(removed, vec!["c.txt"]);
+            }
+            MergeResult::Conflicts(conflicts) => panic!("unexpected conflicts {:?}", conflicts),
+        }
+
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("tex"));
+        assert_eq!(workspace.read("b.txt").as_deref(), Some("main"));
+        assert_eq!(workspace.read("c.txt"), None);
+        assert_eq!(workspace.read("d.txt").as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn merge_reports_files_changed_on_both_sides() {
+        let mut workspace = forked_workspace("merge-conflicts");
+
+        workspace.switch("tex");
+        workspace.write("a.txt", "tex");
+        workspace.remove("b.txt");
+        workspace.write("c.txt", "same");
+        workspace.save();
+
+        workspace.switch("main");
+        workspace.write("a.txt", "main");
+        workspace.write("b.txt", "main");
+        workspace.write("c.txt", "same");
+        workspace.save();
+
+        // the same change on both sides is no conflict, a delete against an edit is
+        match merge(&mut workspace, "tex", &[], &[]) {
+            MergeResult::Conflicts(conflicts) => assert_eq!(conflicts, vec!["a.txt", "b.txt"]),
+            MergeResult::Merged { .. } => panic!("expected conflicts"),
+        }
+
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("main"));
+        assert!(workspace.branches.iter().all(|b| b.pending_merge.is_none()));
+    }
+
+    #[test]
+    fn merge_resolves_conflicts_with_ours_and_theirs() {
+        let mut workspace = forked_workspace("merge-sides");
+
+        workspace.switch("tex");
+        workspace.write("a.txt", "tex");
+        workspace.write("b.txt", "tex");
+        workspace.save();
+
+        workspace.switch("main");
+        workspace.write("a.txt", "main");
+        workspace.write("b.txt", "main");
+        workspace.save();
+
+        match merge(&mut workspace, "tex", &["a.*"], &["b.txt"]) {
+            MergeResult::Merged { updated, .. } => assert_eq!(updated, vec!["b.txt"]),
+            MergeResult::Conflicts(conflicts) => panic!("unexpected conflicts {:?}", conflicts),
+        }
+
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("main"));
+        assert_eq!(workspace.read("b.txt").as_deref(), Some("tex"));
+    }
+
+    #[test]
+    fn merge_refuses_unsaved_changes() {
+        let mut workspace = forked_workspace("merge-dirty");
+
+        workspace.switch("tex");
+        workspace.write("a.txt", "tex");
+        workspace.save();
+
+        workspace.switch("main");
+        workspace.write("b.txt", "unsaved");
+
+        assert!(matches!(workspace.merge_branch("tex", &[], &[]), Err(ModderError::DirtySrc(_))));
+        assert!(matches!(workspace.merge_branch("main", &[], &[]), Err(ModderError::Invalid(_))));
+    }
+
+    #[test]
+    fn merging_again_starts_from_the_saved_merge() {
+        let mut workspace = forked_workspace("merge-again");
+
+        workspace.switch("tex");
+        workspace.write("a.txt", "tex");
+        workspace.save();
+
+        workspace.switch("main");
+        merge(&mut workspace, "tex", &[], &[]);
+        workspace.save();
+
+        workspace.write("a.txt", "main");
+        workspace.save();
+
+        workspace.switch("tex");
+        workspace.write("b.txt", "tex");
+        workspace.save();
+
+        workspace.switch("main");
+
+        // a.txt was only changed on main since the last merge
+        match merge(&mut workspace, "tex", &[], &[]) {
+            MergeResult::Merged { base, updated, .. } => {
+                assert_eq!(base, Some(("tex".to_string(), 1)));
+                assert_eq!(updated, vec!["b.txt"]);
+            }
+            MergeResult::Conflicts(conflicts) => panic!("unexpected conflicts {:?}", conflicts),
+        }
+
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn stash_push_and_pop_round_trip() {
+        let mut workspace = TestWorkspace::new("stash-round-trip");
+
+        for file in ["a.txt", "b.txt"] {
+            workspace.write(file, "saved");
+        }
+
+        workspace.save();
+
+        workspace.write("a.txt", "changed");
+        workspace.remove("b.txt");
+        workspace.write("parts/c.txt", "new");
+
+        assert_eq!(workspace.stash_push(Some("wip".to_string())).unwrap(), 3);
+
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("saved"));
+        assert_eq!(workspace.read("b.txt").as_deref(), Some("saved"));
+        assert_eq!(workspace.read("parts/c.txt"), None);
+        assert!(workspace.status().unwrap().changes.is_empty());
+        assert_eq!(workspace.load_stashes().unwrap().len(), 1);
+
+        let stash = workspace.stash_pop(0).unwrap();
+
+        assert_eq!(stash.message.as_deref(), Some("wip"));
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("changed"));
+        assert_eq!(workspace.read("b.txt"), None);
+        assert_eq!(workspace.read("parts/c.txt").as_deref(), Some("new"));
+        assert!(workspace.load_stashes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn stash_pop_refuses_to_overwrite_changes() {
+        let mut workspace = TestWorkspace::new("stash-pop-dirty");
+        workspace.write("a.txt", "saved");
+        workspace.save();
+
+        workspace.write("a.txt", "stashed");
+        workspace.stash_push(None).unwrap();
+        workspace.write("a.txt", "edited again");
+
+        assert!(matches!(workspace.stash_pop(0), Err(ModderError::DirtySrc(_))));
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("edited again"));
+        assert_eq!(workspace.load_stashes().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unsaved_branch_is_compared_with_where_it_was_created_from() {
+        let mut workspace = forked_workspace("status-unsaved-branch");
+        workspace.switch("tex");
+
+        let status = workspace.status().unwrap();
+
+        assert_eq!(status.version, None);
+        assert_eq!(status.base.map(|b| (b.branch, b.version)), Some(("main".to_string(), 1)));
+        assert!(status.changes.is_empty());
+
+        workspace.write("a.txt", "tex");
+        assert_eq!(workspace.status().unwrap().changes.modified, vec!["a.txt"]);
+
+        // stashing goes back to the version the branch came from
+        workspace.stash_push(None).unwrap();
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("base"));
+        assert_eq!(workspace.read("b.txt").as_deref(), Some("base"));
+
+        assert!(matches!(workspace.switch_branch("main", None, false), Ok(SwitchResult::Success)));
+    }
+
+    #[test]
+    fn branches_cannot_take_the_name_of_a_tag() {
+        let mut workspace = tagged_workspace("branch-tag-name");
+
+        let branch = Branch::new("release".to_string(), String::new(), 1);
+
+        assert!(matches!(workspace.add_branch(branch), Err(ModderError::TagExists(_))));
+        assert!(matches!(workspace.rename_branch("main", "release"), Err(ModderError::TagExists(_))));
+        assert!(matches!(workspace.add_tag("main", "main", 1), Err(ModderError::BranchExists(_))));
+        assert_eq!(resolve(&workspace, "release").unwrap(), ("main".to_string(), 2));
+    }
+
+    #[test]
+    fn branch_references_lists_tags_and_stashes() {
+        let mut workspace = forked_workspace("branch-references");
+
+        workspace.switch("tex");
+        workspace.save();
+        workspace.add_tag("rel", "tex", 1).unwrap();
+        workspace.write("a.txt", "stashed");
+        workspace.stash_push(None).unwrap();
+
+        let (tags, stashes) = workspace.branch_references("tex").unwrap();
+
+        assert_eq!(tags.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["rel"]);
+        assert_eq!(stashes, vec![0]);
+
+        let (tags, stashes) = workspace.branch_references("main").unwrap();
+
+        assert!(tags.is_empty() && stashes.is_empty());
     }
 }
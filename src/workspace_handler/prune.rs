@@ -0,0 +1,461 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use chrono::{DateTime, Duration, Local, Utc};
+
+use super::Workspace;
+use crate::{error::ModderError, manifest::Manifest, tag::Tag};
+
+/// Which saved versions prune is allowed to remove. A version is only removed when
+/// every rule agrees, the latest version and the versions other branches were
+/// created from or merged are always kept.
+#[derive(Debug, Default)]
+pub struct PrunePolicy {
+    pub keep_last: Option<usize>,
+    pub keep_tagged: bool,
+    pub older_than: Option<Duration>,
+    pub keep_daily: bool,
+}
+
+impl PrunePolicy {
+    pub fn is_empty(&self) -> bool {
+        self.keep_last.is_none() && !self.keep_tagged && self.older_than.is_none() && !self.keep_daily
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub versions: Vec<(String, i32)>,
+    /// Tags of removed versions, they go with them
+    pub tags: Vec<Tag>,
+    pub objects: usize,
+    pub bytes: u64,
+}
+
+/// What prune needs to know about a saved version
+struct VersionInfo {
+    version: i32,
+    created: DateTime<Utc>,
+    tagged: bool,
+}
+
+impl Workspace {
+    /// Removes the versions of the given branches the policy doesn't keep, then the stored
+    /// files no version, stash or trashed branch uses anymore. Nothing is removed on a dry run.
+    pub fn prune(&mut self, branches: &[String], policy: &PrunePolicy, dry_run: bool) -> Result<PruneReport, ModderError> {
+        let mut report = PruneReport::default();
+
+        if !policy.is_empty() {
+            for name in branches {
+                for version in self.prunable_versions(name, policy)? {
+                    report.versions.push((name.clone(), version));
+                }
+            }
+        }
+
+        // versions saved before the object store take their whole folder with them
+        for (name, version) in &report.versions {
+            let version_folder = self.version_folder_path(name, *version);
+
+            if version_folder.exists() {
+                report.bytes += Workspace::folder_size(&version_folder)?;
+            }
+        }
+
+        let mut tags = self.load_tags()?;
+
+        report.tags = tags
+            .iter()
+            .filter(|t| report.versions.contains(&(t.branch.clone(), t.version)))
+            .cloned()
+            .collect();
+
+        let referenced = self.referenced_objects(&report.versions)?;
+        let mut garbage = vec![];
+
+        if self.objects_folder_path().exists() {
+            for prefix in std::fs::read_dir(self.objects_folder_path())? {
+                for object in std::fs::read_dir(prefix?.path())? {
+                    let object = object?;
+                    let hash = object.file_name().to_string_lossy().to_string();
+
+                    if !referenced.contains(&hash) {
+                        report.bytes += object.metadata()?.len();
+                        garbage.push(object.path());
+                    }
+                }
+            }
+        }
+
+        report.objects = garbage.len();
+
+        if dry_run {
+            return Ok(report);
+        }
+
+        // drop the versions before their files so an interrupted prune never leaves a version pointing at nothing
+        for (name, version) in &report.versions {
+            let manifest_file = self.manifest_path(name, *version);
+            let version_folder = self.version_folder_path(name, *version);
+
+            if manifest_file.exists() {
+                std::fs::remove_file(manifest_file)?;
+            }
+
+            if version_folder.exists() {
+                std::fs::remove_dir_all(version_folder)?;
+            }
+        }
+
+        for object in garbage {
+            std::fs::remove_file(object)?;
+        }
+
+        // tags of removed versions have nothing left to point to
+        if !report.tags.is_empty() {
+            tags.retain(|t| !report.versions.contains(&(t.branch.clone(), t.version)));
+            self.save_tags(&tags)?;
+        }
+
+        Ok(report)
+    }
+
+    fn prunable_versions(&self, name: &str, policy: &PrunePolicy) -> Result<Vec<i32>, ModderError> {
+        let latest = match self.find_branch(name) {
+            Some(b) => b.latest_version(),
+            None => {
+                return Err(ModderError::BranchNotFound(name.to_string()))
+            }
+        };
+
+        let latest = match latest {
+            Some(l) => l,
+            None => return Ok(vec![]),
+        };
+
+        let tags = self.load_tags()?;
+        let mut versions = vec![];
+
+        for version in 1..=latest {
+            if let Some(info) = self.version_info(name, version, &tags)? {
+                versions.push(info);
+            }
+        }
+
+        // other branches were created from or merged these, merges need them as a base
+        let mut fork_points: HashSet<i32> = self
+            .child_branches(name)
+            .iter()
+            .filter_map(|b| b.parent.as_ref().map(|p| p.version))
+            .collect();
+
+        for branch in &self.branches {
+            let merged = branch.merges.iter().map(|m| &m.source).chain(branch.pending_merge.iter());
+
+            fork_points.extend(merged.filter(|source| source.branch == name).map(|source| source.version));
+        }
+
+        // the newest version of each day survives the daily thinning
+        let mut newest_per_day = HashMap::new();
+
+        for info in &versions {
+            let day = info.created.with_timezone(&Local).date_naive();
+            let newest = newest_per_day.entry(day).or_insert(info.version);
+            *newest = (*newest).max(info.version);
+        }
+
+        let cutoff = policy.older_than.map(|age| Utc::now() - age);
+        let keep_from = versions.len().saturating_sub(policy.keep_last.unwrap_or(0));
+
+        let prunable = versions
+            .iter()
+            .enumerate()
+            .filter(|(index, info)| {
+                let day = info.created.with_timezone(&Local).date_naive();
+
+                info.version != latest
+                    && !fork_points.contains(&info.version)
+                    && *index < keep_from
+                    && !(policy.keep_tagged && info.tagged)
+                    && cutoff.is_none_or(|c| info.created < c)
+                    && !(policy.keep_daily && newest_per_day[&day] == info.version)
+            })
+            .map(|(_, info)| info.version)
+            .collect();
+
+        Ok(prunable)
+    }
+
+    /// None when the version was already pruned
+    fn version_info(&self, name: &str, version: i32, tags: &[Tag]) -> Result<Option<VersionInfo>, ModderError> {
+        let manifest_file = self.manifest_path(name, version);
+
+        if manifest_file.exists() {
+            let manifest = Manifest::load(&manifest_file)?;
+
+            return Ok(Some(VersionInfo {
+                version,
+                created: manifest.created,
+                tagged: tags.iter().any(|t| t.branch == name && t.version == version),
+            }));
+        }
+
+        let version_folder = self.version_folder_path(name, version);
+
+        if version_folder.exists() {
+            return Ok(Some(VersionInfo {
+                version,
+                created: std::fs::metadata(&version_folder)?.modified()?.into(),
+                tagged: tags.iter().any(|t| t.branch == name && t.version == version),
+            }));
+        }
+
+        Ok(None)
+    }
+
+    /// Every hash used by a manifest, a stash or a trashed branch, leaving out the excluded versions
+    fn referenced_objects(&self, excluded: &[(String, i32)]) -> Result<HashSet<String>, ModderError> {
+        let mut referenced = HashSet::new();
+
+        let mut manifests = vec![];
+
+        for branch in &self.branches {
+            for version in 1..branch.version {
+                if !excluded.contains(&(branch.name.clone(), version)) {
+                    manifests.push(self.manifest_path(&branch.name, version));
+                }
+            }
+        }
+
+        for trashed in self.load_trash()? {
+            for version in 1..trashed.branch.version {
+                let trashed_folder = self.trash_folder_path().join(&trashed.id);
+                manifests.push(trashed_folder.join(format!("{}.manifest", version)));
+            }
+        }
+
+        for manifest_file in manifests {
+            if manifest_file.exists() {
+                let manifest = Manifest::load(&manifest_file)?;
+                referenced.extend(manifest.files.into_values().map(|f| f.hash));
+            }
+        }
+
+        for stash in self.load_stashes()? {
+            referenced.extend(stash.files.into_values().map(|f| f.hash));
+        }
+
+        Ok(referenced)
+    }
+
+    fn folder_size(folder: &Path) -> Result<u64, ModderError> {
+        let mut size = 0;
+
+        for entry in std::fs::read_dir(folder)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+
+            size += if metadata.is_dir() {
+                Workspace::folder_size(&entry.path())?
+            } else {
+                metadata.len()
+            };
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use super::PrunePolicy;
+    use crate::{branch::BranchParent, manifest::Manifest, workspace_handler::tests::TestWorkspace};
+
+    /// main with the given number of versions, each changing a.txt
+    fn workspace_with_versions(name: &str, count: usize) -> TestWorkspace {
+        let mut workspace = TestWorkspace::new(name);
+
+        for version in 1..=count {
+            workspace.write("a.txt", &version.to_string());
+            workspace.save();
+        }
+
+        workspace
+    }
+
+    fn set_created(workspace: &TestWorkspace, version: i32, created: DateTime<Utc>) {
+        let manifest_file = workspace.manifest_path("main", version);
+        let mut manifest = Manifest::load(&manifest_file).unwrap();
+
+        manifest.created = created;
+        manifest.save(&manifest_file).unwrap();
+    }
+
+    fn prunable(workspace: &TestWorkspace, policy: PrunePolicy) -> Vec<i32> {
+        workspace.prunable_versions("main", &policy).unwrap()
+    }
+
+    #[test]
+    fn keep_last_keeps_the_newest_versions() {
+        let workspace = workspace_with_versions("prune-keep-last", 5);
+
+        let policy = PrunePolicy {
+            keep_last: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(prunable(&workspace, policy), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn latest_version_is_always_kept() {
+        let workspace = workspace_with_versions("prune-latest", 3);
+
+        let policy = PrunePolicy {
+            keep_last: Some(0),
+            ..Default::default()
+        };
+
+        assert_eq!(prunable(&workspace, policy), vec![1, 2]);
+    }
+
+    #[test]
+    fn keep_tagged_keeps_tagged_versions() {
+        let mut workspace = workspace_with_versions("prune-tagged", 4);
+        workspace.add_tag("release", "main", 2).unwrap();
+
+        let policy = PrunePolicy {
+            keep_tagged: true,
+            ..Default::default()
+        };
+
+        assert_eq!(prunable(&workspace, policy), vec![1, 3]);
+    }
+
+    #[test]
+    fn older_than_only_prunes_old_versions() {
+        let workspace = workspace_with_versions("prune-age", 4);
+
+        set_created(&workspace, 1, Utc::now() - Duration::days(10));
+        set_created(&workspace, 2, Utc::now() - Duration::days(8));
+
+        let policy = PrunePolicy {
+            older_than: Some(Duration::days(7)),
+            ..Default::default()
+        };
+
+        assert_eq!(prunable(&workspace, policy), vec![1, 2]);
+    }
+
+    #[test]
+    fn daily_keeps_the_newest_version_of_each_day() {
+        let workspace = workspace_with_versions("prune-daily", 5);
+
+        // midday so the local date is the same for both versions of a day
+        set_created(&workspace, 1, Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+        set_created(&workspace, 2, Utc.with_ymd_and_hms(2024, 1, 1, 12, 30, 0).unwrap());
+        set_created(&workspace, 3, Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap());
+        set_created(&workspace, 4, Utc.with_ymd_and_hms(2024, 1, 5, 12, 30, 0).unwrap());
+
+        let policy = PrunePolicy {
+            keep_daily: true,
+            ..Default::default()
+        };
+
+        assert_eq!(prunable(&workspace, policy), vec![1, 3]);
+    }
+
+    #[test]
+    fn rules_combine_so_every_rule_has_to_agree() {
+        let workspace = workspace_with_versions("prune-combined", 5);
+
+        set_created(&workspace, 1, Utc::now() - Duration::days(10));
+        set_created(&workspace, 2, Utc::now() - Duration::days(10));
+
+        let policy = PrunePolicy {
+            keep_last: Some(4),
+            older_than: Some(Duration::days(7)),
+            ..Default::default()
+        };
+
+        assert_eq!(prunable(&workspace, policy), vec![1]);
+    }
+
+    #[test]
+    fn fork_points_and_merged_versions_are_kept() {
+        let mut workspace = workspace_with_versions("prune-fork", 2);
+        workspace.create_branch("tex");
+
+        for version in 3..=5 {
+            workspace.write("a.txt", &version.to_string());
+            workspace.save();
+        }
+
+        workspace.switch("tex");
+        workspace.save();
+
+        // a merge of main@3 into tex
+        workspace.branches[1].pending_merge = Some(BranchParent {
+            branch: "main".to_string(),
+            version: 3,
+        });
+
+        let policy = PrunePolicy {
+            keep_last: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(prunable(&workspace, policy), vec![1, 4]);
+    }
+
+    #[test]
+    fn prune_removes_versions_and_unused_files() {
+        let mut workspace = workspace_with_versions("prune-remove", 3);
+
+        let policy = PrunePolicy {
+            keep_last: Some(1),
+            ..Default::default()
+        };
+
+        let dry_run = workspace.prune(&["main".to_string()], &policy, true).unwrap();
+
+        assert_eq!(dry_run.versions, vec![("main".to_string(), 1), ("main".to_string(), 2)]);
+        assert_eq!(dry_run.objects, 2);
+        assert!(workspace.manifest_path("main", 1).exists());
+
+        let report = workspace.prune(&["main".to_string()], &policy, false).unwrap();
+
+        assert_eq!(report.objects, 2);
+        assert!(!workspace.manifest_path("main", 1).exists());
+        assert!(!workspace.manifest_path("main", 2).exists());
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("3"));
+        assert!(workspace.resolve_version("main", Some(3)).is_ok());
+    }
+
+    #[test]
+    fn prune_reports_the_tags_it_removes() {
+        let mut workspace = workspace_with_versions("prune-tags", 3);
+        workspace.add_tag("release-1.2", "main", 1).unwrap();
+        workspace.add_tag("release-1.3", "main", 3).unwrap();
+
+        let policy = PrunePolicy {
+            keep_last: Some(1),
+            ..Default::default()
+        };
+
+        let dry_run = workspace.prune(&["main".to_string()], &policy, true).unwrap();
+
+        assert_eq!(dry_run.tags.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["release-1.2"]);
+        assert_eq!(workspace.load_tags().unwrap().len(), 2);
+
+        workspace.prune(&["main".to_string()], &policy, false).unwrap();
+
+        let tags = workspace.load_tags().unwrap();
+
+        assert_eq!(tags.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["release-1.3"]);
+    }
+}
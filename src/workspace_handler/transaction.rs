@@ -0,0 +1,519 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::Workspace;
+use crate::{
+    branch::MergeRecord,
+    error::{FileFailure, ModderError},
+    manifest::Manifest,
+    metadata,
+    tag::Tag,
+};
+
+/// Written once everything of an operation is staged and verified. From then on the
+/// operation counts as done, an interrupted one is finished by the next command that
+/// loads the workspace with its lock.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "operation")]
+enum Journal {
+    /// A new version of a branch, its manifest is staged as `.staging/manifest`
+    Save {
+        branch: String,
+        version: i32,
+        tags: Vec<String>,
+    },
+    /// The tracked files of src replaced by a saved version, staged as `.staging/src`.
+    /// The replaced files are kept in `.staging/previous` until the journal is gone,
+    /// ignored and filtered out files stay where they are.
+    Checkout { branch: String, version: i32 },
+}
+
+impl Journal {
+    fn describe(&self) -> String {
+        match self {
+            Journal::Save { branch, version, .. } => format!("save of {}@{}", branch, version),
+            Journal::Checkout { branch, version } => format!("checkout of {}@{}", branch, version),
+        }
+    }
+}
+
+impl Workspace {
+    fn staging_folder_path(&self) -> PathBuf {
+        self.root_folder.join(".staging")
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.root_folder.join(".journal")
+    }
+
+    /// Finishes an operation that was interrupted after it was journaled and
+    /// removes whatever an operation left staged before that
+    pub(super) fn recover(&mut self) -> Result<(), ModderError> {
+        let journal_file = self.journal_path();
+
+        if journal_file.exists() {
+            let journal: Journal = serde_json::from_str(&std::fs::read_to_string(&journal_file)?)?;
+
+            self.apply_journal(&journal)?;
+            self.finish_journal()?;
+
+            eprintln!("Finished the interrupted {}.", journal.describe());
+        } else if self.staging_folder_path().exists() {
+            std::fs::remove_dir_all(self.staging_folder_path())?;
+
+            eprintln!("Removed the files of an interrupted operation, nothing was changed.");
+        }
+
+        Ok(())
+    }
+
+    /// Adds a new version to a branch from a manifest whose files are already in the object store
+    pub(super) fn commit_save(&mut self, branch: &str, manifest: &Manifest, tags: Vec<String>) -> Result<(), ModderError> {
+        let staging = self.fresh_staging_folder()?;
+        manifest.save(&staging.join("manifest"))?;
+
+        // every stored file has to be there in full before the version can count
+        let store = self.object_store();
+        let src_folder = self.src_folder_path();
+        let mut failures = vec![];
+
+        for (relative, entry) in &manifest.files {
+            let size = std::fs::metadata(store.object_path(&entry.hash)).map(|m| m.len());
+
+            let error = match size {
+                Ok(size) if size == entry.size => continue,
+                Ok(_) => std::io::Error::new(std::io::ErrorKind::InvalidData, "stored copy is incomplete"),
+                Err(e) => e,
+            };
+
+            failures.push(FileFailure::new(src_folder.join(relative), error));
+        }
+
+        if let Err(e) = ModderError::check_files("store", failures) {
+            _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        self.commit(Journal::Save {
+            branch: branch.to_string(),
+            version: manifest.version,
+            tags,
+        })
+    }
+
+    /// Replaces src with the given snapshot files and makes the branch the current one
+    pub(super) fn commit_checkout(&mut self, branch: &str, version: i32, files: &[(PathBuf, PathBuf)]) -> Result<(), ModderError> {
+        let staging = self.fresh_staging_folder()?;
+        let staged_src = staging.join("src");
+
+        eprintln!("Started copying files to src folder.");
+
+        if let Err(e) = self.stage_files(&staged_src, files) {
+            _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        self.commit(Journal::Checkout {
+            branch: branch.to_string(),
+            version,
+        })?;
+
+        eprintln!("Copied files to src folder.");
+
+        Ok(())
+    }
+
+    fn fresh_staging_folder(&self) -> Result<PathBuf, ModderError> {
+        let staging = self.staging_folder_path();
+
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+
+        std::fs::create_dir_all(&staging)?;
+
+        Ok(staging)
+    }
+
+    /// Copies the files into the folder, failures are reported with the path they would have in src
+    fn stage_files(&self, folder: &Path, files: &[(PathBuf, PathBuf)]) -> Result<(), ModderError> {
+        std::fs::create_dir_all(folder)?;
+
+        let src_folder = self.src_folder_path();
+        let mut failures = vec![];
+
+        for (stored, relative) in files {
+            let staged = folder.join(relative);
+            let copied = staged
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| Workspace::copy_file(stored, &staged));
+
+            if let Err(e) = copied {
+                failures.push(FileFailure::new(src_folder.join(relative), e));
+            }
+        }
+
+        ModderError::check_files("copy", failures)
+    }
+
+    fn commit(&mut self, journal: Journal) -> Result<(), ModderError> {
+        let json = serde_json::to_string_pretty(&journal)?;
+
+        if let Err(e) = metadata::replace(&self.journal_path(), &json) {
+            _ = std::fs::remove_dir_all(self.staging_folder_path());
+            return Err(e.into());
+        }
+
+        let current_branch = self.info.current_branch.clone();
+        let branches = self.branches.clone();
+
+        if let Err(e) = self.apply_journal(&journal) {
+            self.info.current_branch = current_branch;
+            self.branches = branches;
+
+            // if even the rollback fails the journal stays so the next command finishes the operation
+            if self.roll_back(&journal).is_ok() {
+                _ = self.finish_journal();
+            }
+
+            return Err(e);
+        }
+
+        // the operation is done either way, a journal left behind is applied again harmlessly
+        _ = self.finish_journal();
+
+        Ok(())
+    }
+
+    /// Moves the staged files into place and updates the metadata, every step can be
+    /// repeated so an interrupted commit is finished by running it again
+    fn apply_journal(&mut self, journal: &Journal) -> Result<(), ModderError> {
+        let staging = self.staging_folder_path();
+
+        match journal {
+            Journal::Save { branch, version, tags } => {
+                let staged = staging.join("manifest");
+
+                if staged.exists() {
+                    std::fs::create_dir_all(self.branches_folder_path().join(branch))?;
+                    std::fs::rename(&staged, self.manifest_path(branch, *version))?;
+                }
+
+                for b in self.branches.iter_mut().filter(|b| b.name == *branch) {
+                    b.version = b.version.max(version + 1);
+
+                    if let Some(source) = b.pending_merge.take() {
+                        b.merges.push(MergeRecord {
+                            source,
+                            version: *version,
+                        });
+                    }
+                }
+
+                self.save_branches()?;
+
+                let mut saved_tags = self.load_tags()?;
+                let count = saved_tags.len();
+
+                for tag in tags {
+                    if !saved_tags.iter().any(|t| t.name == *tag) {
+                        saved_tags.push(Tag::new(tag.clone(), branch.clone(), *version));
+                    }
+                }
+
+                if saved_tags.len() != count {
+                    self.save_tags(&saved_tags)?;
+                }
+            }
+            Journal::Checkout { branch, .. } => {
+                let src = self.src_folder_path();
+                let staged = staging.join("src");
+                let previous = staging.join("previous");
+                let cleared = staging.join("cleared");
+
+                if staged.exists() {
+                    std::fs::create_dir_all(&src)?;
+
+                    // the tracked files move aside first, the marker keeps a second run
+                    // from mistaking files that were already moved in for old ones
+                    if !cleared.exists() {
+                        for file in Workspace::list_src_files(&src, &self.info, &self.ignore_files_pattern)? {
+                            move_file(&file, &previous.join(file.strip_prefix(&src).unwrap()))?;
+                        }
+
+                        std::fs::write(&cleared, "")?;
+                    }
+
+                    for relative in files_in(&staged)? {
+                        let target = src.join(&relative);
+
+                        // a file the version brings back over an ignored one, keep that one too
+                        if target.exists() && !previous.join(&relative).exists() {
+                            move_file(&target, &previous.join(&relative))?;
+                        }
+
+                        move_file(&staged.join(&relative), &target)?;
+                    }
+
+                    remove_empty_folders(&src)?;
+                    std::fs::remove_dir_all(&staged)?;
+                }
+
+                // whatever was merged into src is gone with it
+                for b in &mut self.branches {
+                    b.pending_merge = None;
+                }
+
+                self.info.current_branch = Some(branch.clone());
+                self.save_info()?;
+                self.save_branches()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Undoes what `apply_journal` got to, the in memory state is already reset by the caller
+    fn roll_back(&mut self, journal: &Journal) -> Result<(), ModderError> {
+        match journal {
+            Journal::Save { branch, version, .. } => {
+                let manifest_file = self.manifest_path(branch, *version);
+
+                if manifest_file.exists() {
+                    std::fs::remove_file(manifest_file)?;
+                }
+
+                self.save_branches()?;
+            }
+            Journal::Checkout { .. } => {
+                let src = self.src_folder_path();
+                let previous = self.staging_folder_path().join("previous");
+
+                // once cleared every tracked file in src came from the version
+                if self.staging_folder_path().join("cleared").exists() {
+                    for file in Workspace::list_src_files(&src, &self.info, &self.ignore_files_pattern)? {
+                        std::fs::remove_file(file)?;
+                    }
+                }
+
+                if previous.exists() {
+                    for relative in files_in(&previous)? {
+                        move_file(&previous.join(&relative), &src.join(&relative))?;
+                    }
+                }
+
+                remove_empty_folders(&src)?;
+
+                self.save_info()?;
+                self.save_branches()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish_journal(&self) -> Result<(), ModderError> {
+        // the journal goes first, leftover staging without a journal is simply removed
+        std::fs::remove_file(self.journal_path())?;
+
+        let staging = self.staging_folder_path();
+
+        if staging.exists() {
+            std::fs::remove_dir_all(staging)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Moves a file, creating the folders it goes into. Renaming single files keeps working
+/// on Windows where a folder another program has open can't be renamed.
+fn move_file(from: &Path, to: &Path) -> Result<(), std::io::Error> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::rename(from, to)
+}
+
+/// Every file below the folder, relative to it
+fn files_in(folder: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut files = vec![];
+    let mut folders = vec![folder.to_path_buf()];
+
+    while let Some(current) = folders.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                folders.push(path);
+            } else {
+                files.push(path.strip_prefix(folder).unwrap().to_path_buf());
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Removes the folders below the given one that have nothing left in them
+fn remove_empty_folders(folder: &Path) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(folder)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            remove_empty_folders(&path)?;
+
+            if std::fs::read_dir(&path)?.next().is_none() {
+                std::fs::remove_dir(&path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Journal;
+    use crate::{
+        ignore::IgnorePatterns,
+        lock::WorkspaceLock,
+        manifest::Manifest,
+        workspace_handler::{tests::TestWorkspace, Workspace},
+    };
+
+    fn write_journal(workspace: &Workspace, journal: &Journal) {
+        std::fs::write(workspace.journal_path(), serde_json::to_string(journal).unwrap()).unwrap();
+    }
+
+    fn reopen(workspace: &Workspace) -> Workspace {
+        let lock = WorkspaceLock::acquire(&workspace.root_folder).unwrap();
+
+        Workspace::open(workspace.root_folder.clone(), Some(&lock)).unwrap()
+    }
+
+    #[test]
+    fn interrupted_save_is_finished_on_load() {
+        let mut workspace = TestWorkspace::new("journal-save");
+        workspace.write("a.txt", "one");
+        workspace.save();
+
+        // version 2 staged and journaled, but the branch still points at version 1
+        let mut manifest = Manifest::new(2, None);
+        manifest.files = Manifest::load(&workspace.manifest_path("main", 1)).unwrap().files;
+
+        std::fs::create_dir_all(workspace.staging_folder_path()).unwrap();
+        manifest.save(&workspace.staging_folder_path().join("manifest")).unwrap();
+
+        write_journal(&workspace, &Journal::Save {
+            branch: "main".to_string(),
+            version: 2,
+            tags: vec!["release".to_string()],
+        });
+
+        let recovered = reopen(&workspace);
+
+        assert_eq!(recovered.find_branch("main").unwrap().latest_version(), Some(2));
+        assert!(recovered.manifest_path("main", 2).exists());
+        assert_eq!(recovered.find_tag("release").unwrap().unwrap().version, 2);
+        assert!(!recovered.journal_path().exists());
+        assert!(!recovered.staging_folder_path().exists());
+    }
+
+    #[test]
+    fn interrupted_checkout_is_finished_on_load() {
+        let mut workspace = TestWorkspace::new("journal-checkout");
+        workspace.write("a.txt", "one");
+        workspace.save();
+        workspace.write("a.txt", "two");
+        workspace.save();
+
+        // version 1 staged and the tracked files already moved aside when it stopped
+        let staging = workspace.staging_folder_path();
+        std::fs::create_dir_all(staging.join("src")).unwrap();
+        std::fs::create_dir_all(staging.join("previous")).unwrap();
+        std::fs::write(staging.join("src/a.txt"), "one").unwrap();
+        std::fs::rename(workspace.src_folder_path().join("a.txt"), staging.join("previous/a.txt")).unwrap();
+        std::fs::write(staging.join("cleared"), "").unwrap();
+
+        write_journal(&workspace, &Journal::Checkout {
+            branch: "main".to_string(),
+            version: 1,
+        });
+
+        let recovered = reopen(&workspace);
+
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("one"));
+        assert_eq!(recovered.info.current_branch.as_deref(), Some("main"));
+        assert!(!recovered.journal_path().exists());
+        assert!(!recovered.staging_folder_path().exists());
+    }
+
+    #[test]
+    fn staging_without_a_journal_is_thrown_away() {
+        let mut workspace = TestWorkspace::new("journal-none");
+        workspace.write("a.txt", "one");
+        workspace.save();
+
+        let staging = workspace.staging_folder_path();
+        std::fs::create_dir_all(staging.join("src")).unwrap();
+        std::fs::write(staging.join("src/a.txt"), "half copied").unwrap();
+
+        let recovered = reopen(&workspace);
+
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("one"));
+        assert_eq!(recovered.find_branch("main").unwrap().latest_version(), Some(1));
+        assert!(!recovered.staging_folder_path().exists());
+    }
+
+    #[test]
+    fn read_only_load_leaves_the_journal_alone() {
+        let mut workspace = TestWorkspace::new("journal-read-only");
+        workspace.write("a.txt", "one");
+        workspace.save();
+
+        write_journal(&workspace, &Journal::Checkout {
+            branch: "main".to_string(),
+            version: 1,
+        });
+
+        Workspace::open(workspace.root_folder.clone(), None).unwrap();
+
+        assert!(workspace.journal_path().exists());
+    }
+
+    #[test]
+    fn checkout_keeps_ignored_and_filtered_files() {
+        let mut workspace = TestWorkspace::new("journal-ignored");
+        workspace.ignore_files_pattern = IgnorePatterns::parse("*.bak\ntmp/").unwrap();
+
+        workspace.write("a.txt", "one");
+        workspace.write("old/b.txt", "one");
+        workspace.save();
+        workspace.remove("old/b.txt");
+        workspace.write("a.txt", "two");
+        workspace.save();
+
+        workspace.write("x.bak", "ignored");
+        workspace.write("tmp/t.txt", "ignored");
+
+        workspace.restore_version("main", Some(1), false).unwrap();
+
+        assert_eq!(workspace.read("a.txt").as_deref(), Some("one"));
+        assert_eq!(workspace.read("old/b.txt").as_deref(), Some("one"));
+        assert_eq!(workspace.read("x.bak").as_deref(), Some("ignored"));
+        assert_eq!(workspace.read("tmp/t.txt").as_deref(), Some("ignored"));
+
+        // src now differs from the latest version, that's what restoring it back is for
+        workspace.restore_version("main", Some(2), true).unwrap();
+
+        // the folder only held a tracked file
+        assert!(!workspace.src_folder_path().join("old").exists());
+        assert_eq!(workspace.read("x.bak").as_deref(), Some("ignored"));
+        assert!(!workspace.staging_folder_path().exists());
+    }
+}